@@ -0,0 +1,30 @@
+use crate::types::DEFAULT_QUEUE;
+use axum::extract::rejection::PathRejection;
+use axum::extract::{FromRequestParts, OptionalFromRequestParts, Path};
+use axum::http::request::Parts;
+use std::collections::HashMap;
+
+/// Name of the queue a request operates on.
+/// Taken from the `{queue}` path segment, or the default queue for unprefixed routes.
+pub struct QueueName(pub String);
+
+impl<S> FromRequestParts<S> for QueueName
+where
+    S: Send + Sync,
+{
+    type Rejection = PathRejection;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let params =
+            <Path<HashMap<String, String>> as OptionalFromRequestParts<S>>::from_request_parts(
+                parts, state,
+            )
+            .await?;
+
+        let name = params
+            .and_then(|Path(mut params)| params.remove("queue"))
+            .unwrap_or_else(|| DEFAULT_QUEUE.to_string());
+
+        Ok(QueueName(name))
+    }
+}
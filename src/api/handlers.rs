@@ -1,66 +1,324 @@
+use crate::api::extractors::QueueName;
 use crate::api::models::{
-    AddMessageRequest, DeleteMessagesRequest, GetMessagesRequest, RetryMessagesRequest,
+    AddBatchRequest, AddMessageRequest, BrowseMessagesQuery, CreateQueueRequest, DeadLetterPath,
+    DeleteMessagesRequest, ExtendLocksRequest, GetMessagesRequest, ImportSnapshotQuery,
+    ListDeadLettersQuery, MessagePath, RedriveMessagesRequest, RetryMessagesRequest,
 };
+use crate::errors::Error;
 use crate::services::MessageService;
-use crate::types::{Message, QueueStats};
-use axum::extract::State;
+use crate::storage::queue_not_found;
+use crate::types::{
+    AddOptions, AddOutcome, BodyEncoding, DeadLetter, ImportSummary, Message, MessagePage,
+    QueueConfig, QueueConfigUpdate, QueueInfo, QueueStats, ReceiptOutcome, RedriveOutcome,
+};
+use axum::body::Bytes;
+use axum::extract::{Path, Query, State};
+use axum::http::{header, HeaderMap};
+use axum::response::{IntoResponse, Response};
 use axum::Json;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
 use skyak_axum_core::errors::ApiError;
 use skyak_axum_core::https::{error, success, ApiResponse};
+use std::collections::BTreeMap;
+
+/// Prefix of the headers carrying the attributes of a binary message
+const ATTRIBUTE_HEADER_PREFIX: &str = "x-tlq-attribute-";
+
+impl From<Error> for ApiError {
+    fn from(err: Error) -> Self {
+        match err {
+            Error::Validation(message) => ApiError::BadRequest(Some(message)),
+            Error::NotFound(message) => ApiError::NotFound(Some(message)),
+            Error::Conflict(message) => ApiError::Conflict(Some(message)),
+            Error::PayloadTooLarge(message) => ApiError::Other(413, Some(message)),
+            Error::Storage(message) => ApiError::InternalServerError(Some(message)),
+            Error::Unavailable(message) => ApiError::ServiceUnavailable(Some(message)),
+        }
+    }
+}
+
+pub async fn list_queues(State(service): State<MessageService>) -> ApiResponse<Vec<QueueInfo>> {
+    match service.list_queues().await {
+        Ok(queues) => success(queues),
+        Err(err) => error(err.into()),
+    }
+}
+
+pub async fn create_queue(
+    State(service): State<MessageService>,
+    Json(request): Json<CreateQueueRequest>,
+) -> ApiResponse<QueueConfig> {
+    match service.create_queue(&request.name, request.config).await {
+        Ok(config) => success(config),
+        Err(err) => error(err.into()),
+    }
+}
+
+pub async fn delete_queue(
+    State(service): State<MessageService>,
+    QueueName(queue): QueueName,
+) -> ApiResponse<String> {
+    match service.delete_queue(&queue).await {
+        Ok(_) => success("Success".to_string()),
+        Err(err) => error(err.into()),
+    }
+}
+
+pub async fn get_queue_config(
+    State(service): State<MessageService>,
+    QueueName(queue): QueueName,
+) -> ApiResponse<QueueConfig> {
+    match service.queue_config(&queue).await {
+        Ok(Some(config)) => success(config),
+        Ok(None) => error(queue_not_found(&queue).into()),
+        Err(err) => error(err.into()),
+    }
+}
 
-pub async fn stats(State(service): State<MessageService>) -> ApiResponse<QueueStats> {
-    match service.stats().await {
+pub async fn update_queue_config(
+    State(service): State<MessageService>,
+    QueueName(queue): QueueName,
+    Json(request): Json<QueueConfigUpdate>,
+) -> ApiResponse<QueueConfig> {
+    match service.update_queue_config(&queue, request).await {
+        Ok(config) => success(config),
+        Err(err) => error(err.into()),
+    }
+}
+
+pub async fn stats(
+    State(service): State<MessageService>,
+    QueueName(queue): QueueName,
+) -> ApiResponse<QueueStats> {
+    match service.stats(&queue).await {
         Ok(stats) => success(stats),
-        Err(message) => error(ApiError::BadRequest(Some(message))),
+        Err(err) => error(err.into()),
     }
 }
 
 pub async fn add_message(
     State(service): State<MessageService>,
+    QueueName(queue): QueueName,
     Json(request): Json<AddMessageRequest>,
 ) -> ApiResponse<Message> {
-    match service.add(request.body).await {
+    match service.add(&queue, request.body, request.options).await {
+        Ok(message) => success(message),
+        Err(err) => error(err.into()),
+    }
+}
+
+pub async fn add_batch(
+    State(service): State<MessageService>,
+    QueueName(queue): QueueName,
+    Json(request): Json<AddBatchRequest>,
+) -> ApiResponse<Vec<AddOutcome>> {
+    let items = request
+        .messages
+        .into_iter()
+        .map(|message| (message.body, message.options))
+        .collect();
+    match service.add_batch(&queue, items).await {
+        Ok(outcomes) => success(outcomes),
+        Err(err) => error(err.into()),
+    }
+}
+
+/// Adds a message with a binary body, taken verbatim from the request body.
+/// The message is stored and returned with its body encoded as base64.
+pub async fn add_binary_message(
+    State(service): State<MessageService>,
+    QueueName(queue): QueueName,
+    Query(mut options): Query<AddOptions>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> ApiResponse<Message> {
+    options.encoding = BodyEncoding::Base64;
+    match header_attributes(&headers) {
+        Ok(attributes) => options.attributes = attributes,
+        Err(err) => return error(err.into()),
+    }
+    match service.add(&queue, BASE64.encode(body), options).await {
         Ok(message) => success(message),
-        Err(message) => error(ApiError::BadRequest(Some(message))),
+        Err(err) => error(err.into()),
     }
 }
 
+/// Collects message attributes from `X-TLQ-Attribute-<name>` headers. Header
+/// names are case-insensitive, so attribute names come out lowercase.
+fn header_attributes(headers: &HeaderMap) -> Result<BTreeMap<String, String>, Error> {
+    let mut attributes = BTreeMap::new();
+    for (name, value) in headers {
+        let Some(attribute) = name.as_str().strip_prefix(ATTRIBUTE_HEADER_PREFIX) else {
+            continue;
+        };
+        let value = value.to_str().map_err(|_| {
+            Error::Validation(format!(
+                "Attribute '{attribute}' must be printable ASCII in a header"
+            ))
+        })?;
+        attributes.insert(attribute.to_string(), value.to_string());
+    }
+    Ok(attributes)
+}
+
 pub async fn get_messages(
     State(service): State<MessageService>,
+    QueueName(queue): QueueName,
     Json(request): Json<GetMessagesRequest>,
 ) -> ApiResponse<Vec<Message>> {
     let count = request.count.unwrap_or(1);
-    match service.get(count).await {
+    match service
+        .get(&queue, count, request.lock_secs, request.wait_secs)
+        .await
+    {
         Ok(messages) => success(messages),
-        Err(message) => error(ApiError::BadRequest(Some(message))),
+        Err(err) => error(err.into()),
     }
 }
 
 pub async fn delete_messages(
     State(service): State<MessageService>,
+    QueueName(queue): QueueName,
     Json(request): Json<DeleteMessagesRequest>,
-) -> ApiResponse<String> {
-    let ids = request.ids;
-    match service.delete(ids).await {
-        Ok(_) => success("Success".to_string()),
-        Err(message) => error(ApiError::BadRequest(Some(message))),
+) -> ApiResponse<Vec<ReceiptOutcome>> {
+    match service.delete(&queue, request.receipts).await {
+        Ok(outcomes) => success(outcomes),
+        Err(err) => error(err.into()),
     }
 }
 
-pub async fn purge_messages(State(service): State<MessageService>) -> ApiResponse<String> {
-    match service.purge().await {
+pub async fn purge_messages(
+    State(service): State<MessageService>,
+    QueueName(queue): QueueName,
+) -> ApiResponse<String> {
+    match service.purge(&queue).await {
         Ok(_) => success("Success".to_string()),
-        Err(message) => error(ApiError::BadRequest(Some(message))),
+        Err(err) => error(err.into()),
     }
 }
 
 pub async fn retry_messages(
     State(service): State<MessageService>,
+    QueueName(queue): QueueName,
     Json(request): Json<RetryMessagesRequest>,
-) -> ApiResponse<String> {
+) -> ApiResponse<Vec<ReceiptOutcome>> {
+    match service.retry(&queue, request.receipts).await {
+        Ok(outcomes) => success(outcomes),
+        Err(err) => error(err.into()),
+    }
+}
+
+pub async fn extend_locks(
+    State(service): State<MessageService>,
+    QueueName(queue): QueueName,
+    Json(request): Json<ExtendLocksRequest>,
+) -> ApiResponse<Vec<Message>> {
+    match service
+        .extend(&queue, request.receipts, request.extend_secs)
+        .await
+    {
+        Ok(messages) => success(messages),
+        Err(err) => error(err.into()),
+    }
+}
+
+pub async fn browse_messages(
+    State(service): State<MessageService>,
+    QueueName(queue): QueueName,
+    Query(query): Query<BrowseMessagesQuery>,
+) -> ApiResponse<MessagePage> {
+    let limit = query.limit.unwrap_or(100);
+    match service
+        .browse(&queue, query.state, query.after, limit)
+        .await
+    {
+        Ok(page) => success(page),
+        Err(err) => error(err.into()),
+    }
+}
+
+pub async fn get_message(
+    State(service): State<MessageService>,
+    QueueName(queue): QueueName,
+    Path(path): Path<MessagePath>,
+) -> ApiResponse<Message> {
+    match service.find(&queue, path.id).await {
+        Ok(Some(message)) => success(message),
+        Ok(None) => error(ApiError::NotFound(Some("Message not found".to_string()))),
+        Err(err) => error(err.into()),
+    }
+}
+
+pub async fn list_dead_letters(
+    State(service): State<MessageService>,
+    QueueName(queue): QueueName,
+    Query(query): Query<ListDeadLettersQuery>,
+) -> ApiResponse<Vec<DeadLetter>> {
+    let limit = query.limit.unwrap_or(100);
+    match service.list_dead(&queue, limit).await {
+        Ok(dead_letters) => success(dead_letters),
+        Err(err) => error(err.into()),
+    }
+}
+
+pub async fn get_dead_letter(
+    State(service): State<MessageService>,
+    QueueName(queue): QueueName,
+    Path(path): Path<DeadLetterPath>,
+) -> ApiResponse<DeadLetter> {
+    match service.get_dead(&queue, path.id).await {
+        Ok(Some(dead_letter)) => success(dead_letter),
+        Ok(None) => error(ApiError::NotFound(Some(
+            "Dead letter not found".to_string(),
+        ))),
+        Err(err) => error(err.into()),
+    }
+}
+
+pub async fn redrive_dead_letters(
+    State(service): State<MessageService>,
+    QueueName(queue): QueueName,
+    Json(request): Json<RedriveMessagesRequest>,
+) -> ApiResponse<Vec<RedriveOutcome>> {
     let ids = request.ids;
-    match service.retry(ids).await {
+    match service.redrive(&queue, ids).await {
+        Ok(outcomes) => success(outcomes),
+        Err(err) => error(err.into()),
+    }
+}
+
+pub async fn purge_dead_letters(
+    State(service): State<MessageService>,
+    QueueName(queue): QueueName,
+) -> ApiResponse<String> {
+    match service.purge_dead(&queue).await {
         Ok(_) => success("Success".to_string()),
-        Err(message) => error(ApiError::BadRequest(Some(message))),
+        Err(err) => error(err.into()),
+    }
+}
+
+pub async fn export_snapshot(
+    State(service): State<MessageService>,
+    QueueName(queue): QueueName,
+) -> Response {
+    match service.export(&queue).await {
+        Ok(snapshot) => {
+            ([(header::CONTENT_TYPE, "application/x-ndjson")], snapshot).into_response()
+        }
+        Err(err) => ApiError::from(err).into_response(),
+    }
+}
+
+pub async fn import_snapshot(
+    State(service): State<MessageService>,
+    QueueName(queue): QueueName,
+    Query(query): Query<ImportSnapshotQuery>,
+    body: String,
+) -> ApiResponse<ImportSummary> {
+    let processing = query.processing.unwrap_or_default();
+    match service.import(&queue, &body, processing).await {
+        Ok(summary) => success(summary),
+        Err(err) => error(err.into()),
     }
 }
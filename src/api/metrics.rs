@@ -0,0 +1,41 @@
+use crate::metrics::metrics;
+use crate::services::MessageService;
+use axum::extract::{MatchedPath, Request, State};
+use axum::http::header;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use skyak_axum_core::errors::ApiError;
+use std::time::Instant;
+
+/// Content type of the Prometheus text exposition format
+const TEXT_FORMAT: &str = "text/plain; version=0.0.4";
+
+/// Serves all metrics in the Prometheus text format, refreshing the queue gauges first.
+pub async fn render(State(service): State<MessageService>) -> Response {
+    match service.list_queues().await {
+        Ok(queues) => {
+            metrics().set_queue_stats(&queues);
+            ([(header::CONTENT_TYPE, TEXT_FORMAT)], metrics().render()).into_response()
+        }
+        Err(err) => ApiError::from(err).into_response(),
+    }
+}
+
+/// Records the latency of every request to a known route.
+pub async fn track(request: Request, next: Next) -> Response {
+    let method = request.method().to_string();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_default();
+    let start = Instant::now();
+
+    let response = next.run(request).await;
+
+    metrics()
+        .http_duration
+        .with_label_values(&[&method, &route, response.status().as_str()])
+        .observe(start.elapsed().as_secs_f64());
+    response
+}
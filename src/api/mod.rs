@@ -1,19 +1,58 @@
+use crate::config::config;
 use crate::services::MessageService;
-use axum::routing::{get, post};
+use axum::extract::DefaultBodyLimit;
+use axum::middleware;
+use axum::routing::{delete, get, post};
 use axum::Router;
 
+mod extractors;
 mod handlers;
 mod health;
+mod metrics;
 mod models;
 
 pub fn create_api(service: MessageService) -> Router {
     Router::new()
         .route("/hello", get(health::check))
+        .route("/metrics", get(metrics::render))
+        .route(
+            "/queues",
+            get(handlers::list_queues).post(handlers::create_queue),
+        )
+        .route("/queues/{queue}", delete(handlers::delete_queue))
+        .nest("/queues/{queue}", queue_routes())
+        .merge(queue_routes())
+        .route_layer(middleware::from_fn(metrics::track))
+        .with_state(service)
+}
+
+/// Routes operating on a single queue.
+/// Served both under `/queues/{queue}` and unprefixed for the default queue.
+fn queue_routes() -> Router<MessageService> {
+    Router::new()
+        .route(
+            "/config",
+            get(handlers::get_queue_config).post(handlers::update_queue_config),
+        )
         .route("/stats", get(handlers::stats))
         .route("/add", post(handlers::add_message))
+        .route("/add/batch", post(handlers::add_batch))
+        .route("/add/binary", post(handlers::add_binary_message))
         .route("/get", post(handlers::get_messages))
         .route("/delete", post(handlers::delete_messages))
         .route("/purge", post(handlers::purge_messages))
         .route("/retry", post(handlers::retry_messages))
-        .with_state(service)
+        .route("/extend", post(handlers::extend_locks))
+        .route("/messages", get(handlers::browse_messages))
+        .route("/messages/{id}", get(handlers::get_message))
+        .route("/dead", get(handlers::list_dead_letters))
+        .route("/dead/{id}", get(handlers::get_dead_letter))
+        .route("/dead/redrive", post(handlers::redrive_dead_letters))
+        .route("/dead/purge", post(handlers::purge_dead_letters))
+        .route(
+            "/snapshot",
+            get(handlers::export_snapshot)
+                .post(handlers::import_snapshot)
+                .layer(DefaultBodyLimit::max(config().max_snapshot_size)),
+        )
 }
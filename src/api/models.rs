@@ -1,21 +1,77 @@
+use crate::types::{AddOptions, MessageState, ProcessingRestore, QueueConfigUpdate};
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct AddMessageRequest {
     pub body: String,
+    #[serde(flatten)]
+    pub options: AddOptions,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AddBatchRequest {
+    pub messages: Vec<AddMessageRequest>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct GetMessagesRequest {
     pub count: Option<usize>,
+    pub lock_secs: Option<u64>,
+    pub wait_secs: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BrowseMessagesQuery {
+    pub state: Option<MessageState>,
+    pub after: Option<Uuid>,
+    pub limit: Option<usize>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ImportSnapshotQuery {
+    pub processing: Option<ProcessingRestore>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct MessagePath {
+    pub id: String,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct DeleteMessagesRequest {
-    pub ids: Vec<String>,
+    pub receipts: Vec<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct RetryMessagesRequest {
+    pub receipts: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ExtendLocksRequest {
+    pub receipts: Vec<String>,
+    pub extend_secs: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CreateQueueRequest {
+    pub name: String,
+    #[serde(flatten)]
+    pub config: QueueConfigUpdate,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct DeadLetterPath {
+    pub id: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ListDeadLettersQuery {
+    pub limit: Option<usize>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RedriveMessagesRequest {
     pub ids: Vec<String>,
 }
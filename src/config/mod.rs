@@ -1,3 +1,4 @@
+use crate::types::QueueConfig;
 use std::env;
 use std::sync::OnceLock;
 use tracing::Level;
@@ -7,6 +8,31 @@ const DEFAULT_MAX_MESSAGE_SIZE: usize = 65536; // 64KB
 const DEFAULT_LOG_LEVEL: &str = "info";
 const DEFAULT_LOCK_DURATION_SECS: u64 = 60;
 const DEFAULT_MAX_RETRIES: u32 = 3;
+const DEFAULT_MESSAGE_TTL_SECS: u64 = 0; // never expire
+const DEFAULT_MAX_LOCK_DURATION_SECS: u64 = 43200; // 12 hours
+const DEFAULT_DEDUP_WINDOW_SECS: u64 = 300; // 5 minutes
+const DEFAULT_STORAGE_PATH: &str = "tlq.log";
+const DEFAULT_SHUTDOWN_GRACE_SECS: u64 = 30;
+const DEFAULT_SNAPSHOT_INTERVAL_SECS: u64 = 60;
+const DEFAULT_MAX_SNAPSHOT_SIZE: usize = 64 * 1024 * 1024; // 64MB
+
+/// Storage backend used to hold queued messages
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StorageBackend {
+    /// Messages are kept in memory only and lost on restart
+    Memory,
+    /// Messages are kept in memory and recorded in an append-only log on disk
+    Log,
+}
+
+/// When the log backend forces its writes to disk
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StorageSync {
+    /// Every write is synced to disk before the request returns
+    Always,
+    /// Writes are left to the OS to flush; a power loss can drop recent changes
+    Never,
+}
 
 #[derive(Debug, Clone)]
 pub struct Config {
@@ -14,8 +40,22 @@ pub struct Config {
     pub max_message_size: usize,
     pub log_level: String,
     pub lock_duration_secs: u64,
+    pub max_lock_duration_secs: u64,
     pub max_retries: u32,
+    pub message_ttl_secs: u64,
+    pub dedup_window_secs: u64,
     pub worker_interval_secs: u64,
+    pub storage: StorageBackend,
+    pub storage_path: String,
+    pub storage_sync: StorageSync,
+    pub shutdown_grace_secs: u64,
+    /// File the memory backend snapshots its queues to; snapshots are off if unset
+    pub snapshot_path: Option<String>,
+    pub snapshot_interval_secs: u64,
+    /// Largest snapshot accepted for import, in bytes
+    pub max_snapshot_size: usize,
+    /// Options set in the environment that the chosen storage backend does not use
+    pub ignored_options: Vec<&'static str>,
 }
 
 impl Default for Config {
@@ -25,8 +65,19 @@ impl Default for Config {
             max_message_size: DEFAULT_MAX_MESSAGE_SIZE,
             log_level: DEFAULT_LOG_LEVEL.to_string(),
             lock_duration_secs: DEFAULT_LOCK_DURATION_SECS,
+            max_lock_duration_secs: DEFAULT_MAX_LOCK_DURATION_SECS,
             max_retries: DEFAULT_MAX_RETRIES,
+            message_ttl_secs: DEFAULT_MESSAGE_TTL_SECS,
+            dedup_window_secs: DEFAULT_DEDUP_WINDOW_SECS,
             worker_interval_secs: (DEFAULT_LOCK_DURATION_SECS / 5).max(5),
+            storage: StorageBackend::Memory,
+            storage_path: DEFAULT_STORAGE_PATH.to_string(),
+            storage_sync: StorageSync::Always,
+            shutdown_grace_secs: DEFAULT_SHUTDOWN_GRACE_SECS,
+            snapshot_path: None,
+            snapshot_interval_secs: DEFAULT_SNAPSHOT_INTERVAL_SECS,
+            max_snapshot_size: DEFAULT_MAX_SNAPSHOT_SIZE,
+            ignored_options: Vec::new(),
         }
     }
 }
@@ -59,12 +110,32 @@ impl Config {
             }
         }
 
+        if let Ok(env_value) = env::var("TLQ_MAX_LOCK_DURATION") {
+            if let Ok(secs) = env_value.parse::<u64>() {
+                if secs > 0 {
+                    config.max_lock_duration_secs = secs;
+                }
+            }
+        }
+
         if let Ok(env_value) = env::var("TLQ_MAX_RETRIES") {
             if let Ok(retries) = env_value.parse::<u32>() {
                 config.max_retries = retries;
             }
         }
 
+        if let Ok(env_value) = env::var("TLQ_MESSAGE_TTL") {
+            if let Ok(secs) = env_value.parse::<u64>() {
+                config.message_ttl_secs = secs;
+            }
+        }
+
+        if let Ok(env_value) = env::var("TLQ_DEDUP_WINDOW") {
+            if let Ok(secs) = env_value.parse::<u64>() {
+                config.dedup_window_secs = secs;
+            }
+        }
+
         config.worker_interval_secs = (config.lock_duration_secs / 5).max(5);
 
         if let Ok(env_value) = env::var("TLQ_WORKER_INTERVAL") {
@@ -75,6 +146,64 @@ impl Config {
             }
         }
 
+        if let Ok(env_value) = env::var("TLQ_STORAGE") {
+            match env_value.to_lowercase().as_str() {
+                "memory" => config.storage = StorageBackend::Memory,
+                "log" => config.storage = StorageBackend::Log,
+                _ => {}
+            }
+        }
+
+        if let Ok(env_value) = env::var("TLQ_STORAGE_PATH") {
+            if !env_value.is_empty() {
+                config.storage_path = env_value;
+            }
+        }
+
+        if let Ok(env_value) = env::var("TLQ_STORAGE_SYNC") {
+            match env_value.to_lowercase().as_str() {
+                "always" => config.storage_sync = StorageSync::Always,
+                "never" => config.storage_sync = StorageSync::Never,
+                _ => {}
+            }
+        }
+
+        if let Ok(env_value) = env::var("TLQ_SHUTDOWN_GRACE") {
+            if let Ok(secs) = env_value.parse::<u64>() {
+                config.shutdown_grace_secs = secs;
+            }
+        }
+
+        if let Ok(env_value) = env::var("TLQ_SNAPSHOT_PATH") {
+            if !env_value.is_empty() {
+                config.snapshot_path = Some(env_value);
+            }
+        }
+
+        if let Ok(env_value) = env::var("TLQ_SNAPSHOT_INTERVAL") {
+            if let Ok(secs) = env_value.parse::<u64>() {
+                if secs > 0 {
+                    config.snapshot_interval_secs = secs;
+                }
+            }
+        }
+
+        if let Ok(env_value) = env::var("TLQ_MAX_SNAPSHOT_SIZE") {
+            if let Some(size) = Self::parse_size(&env_value) {
+                config.max_snapshot_size = size;
+            }
+        }
+
+        let unused: &[&'static str] = match config.storage {
+            StorageBackend::Memory => &["TLQ_STORAGE_PATH", "TLQ_STORAGE_SYNC"],
+            StorageBackend::Log => &["TLQ_SNAPSHOT_PATH", "TLQ_SNAPSHOT_INTERVAL"],
+        };
+        config.ignored_options = unused
+            .iter()
+            .copied()
+            .filter(|name| env::var_os(name).is_some())
+            .collect();
+
         config
     }
 
@@ -88,12 +217,30 @@ impl Config {
                 .parse::<usize>()
                 .ok()
                 .filter(|&kb| kb > 0)
-                .map(|kb| kb * 1024)
+                .and_then(|kb| kb.checked_mul(1024))
+        } else if let Some(mb_str) = value.strip_suffix(['M', 'm']) {
+            mb_str
+                .parse::<usize>()
+                .ok()
+                .filter(|&mb| mb > 0)
+                .and_then(|mb| mb.checked_mul(1024 * 1024))
         } else {
             value.parse::<usize>().ok().filter(|&bytes| bytes > 0)
         }
     }
 
+    /// Queue settings applied to queues created without explicit values
+    pub fn queue_defaults(&self) -> QueueConfig {
+        QueueConfig {
+            lock_duration_secs: self.lock_duration_secs,
+            max_retries: self.max_retries,
+            max_message_size: self.max_message_size,
+            ttl_secs: self.message_ttl_secs,
+            dead_letter_expired: false,
+            dedup_window_secs: self.dedup_window_secs,
+        }
+    }
+
     pub fn tracing_level(&self) -> Level {
         match self.log_level.to_lowercase().as_str() {
             "trace" => Level::TRACE,
@@ -137,8 +284,18 @@ mod tests {
         env::remove_var("TLQ_MAX_MESSAGE_SIZE");
         env::remove_var("TLQ_LOG_LEVEL");
         env::remove_var("TLQ_LOCK_DURATION");
+        env::remove_var("TLQ_MAX_LOCK_DURATION");
         env::remove_var("TLQ_MAX_RETRIES");
+        env::remove_var("TLQ_MESSAGE_TTL");
+        env::remove_var("TLQ_DEDUP_WINDOW");
         env::remove_var("TLQ_WORKER_INTERVAL");
+        env::remove_var("TLQ_STORAGE");
+        env::remove_var("TLQ_STORAGE_PATH");
+        env::remove_var("TLQ_STORAGE_SYNC");
+        env::remove_var("TLQ_SHUTDOWN_GRACE");
+        env::remove_var("TLQ_SNAPSHOT_PATH");
+        env::remove_var("TLQ_SNAPSHOT_INTERVAL");
+        env::remove_var("TLQ_MAX_SNAPSHOT_SIZE");
     }
 
     #[test]
@@ -150,8 +307,25 @@ mod tests {
         assert_eq!(config.max_message_size, DEFAULT_MAX_MESSAGE_SIZE);
         assert_eq!(config.log_level, DEFAULT_LOG_LEVEL);
         assert_eq!(config.lock_duration_secs, DEFAULT_LOCK_DURATION_SECS);
+        assert_eq!(
+            config.max_lock_duration_secs,
+            DEFAULT_MAX_LOCK_DURATION_SECS
+        );
         assert_eq!(config.max_retries, DEFAULT_MAX_RETRIES);
+        assert_eq!(config.message_ttl_secs, DEFAULT_MESSAGE_TTL_SECS);
+        assert_eq!(config.dedup_window_secs, DEFAULT_DEDUP_WINDOW_SECS);
         assert_eq!(config.worker_interval_secs, 12); // 60 / 5 = 12
+        assert_eq!(config.storage, StorageBackend::Memory);
+        assert_eq!(config.storage_path, DEFAULT_STORAGE_PATH);
+        assert_eq!(config.storage_sync, StorageSync::Always);
+        assert_eq!(config.shutdown_grace_secs, DEFAULT_SHUTDOWN_GRACE_SECS);
+        assert_eq!(config.snapshot_path, None);
+        assert_eq!(
+            config.snapshot_interval_secs,
+            DEFAULT_SNAPSHOT_INTERVAL_SECS
+        );
+        assert_eq!(config.max_snapshot_size, DEFAULT_MAX_SNAPSHOT_SIZE);
+        assert!(config.ignored_options.is_empty());
     }
 
     #[test]
@@ -285,6 +459,27 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_max_lock_durations() {
+        let test_cases = vec![
+            ("3600", 3600, "valid seconds"),
+            ("0", DEFAULT_MAX_LOCK_DURATION_SECS, "zero value"),
+            ("abc", DEFAULT_MAX_LOCK_DURATION_SECS, "invalid string"),
+            ("", DEFAULT_MAX_LOCK_DURATION_SECS, "empty string"),
+        ];
+
+        for (input, expected, description) in test_cases {
+            with_env_var("TLQ_MAX_LOCK_DURATION", input, || {
+                let config = Config::from_env();
+                assert_eq!(
+                    config.max_lock_duration_secs, expected,
+                    "Failed for {}: input '{}'",
+                    description, input
+                );
+            });
+        }
+    }
+
     #[test]
     fn test_max_retries() {
         let test_cases = vec![
@@ -307,6 +502,70 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_message_ttls() {
+        let test_cases = vec![
+            ("3600", 3600, "valid seconds"),
+            ("0", 0, "zero disables expiry"),
+            ("abc", DEFAULT_MESSAGE_TTL_SECS, "invalid string"),
+            ("", DEFAULT_MESSAGE_TTL_SECS, "empty string"),
+            ("-1", DEFAULT_MESSAGE_TTL_SECS, "negative value"),
+        ];
+
+        for (input, expected, description) in test_cases {
+            with_env_var("TLQ_MESSAGE_TTL", input, || {
+                let config = Config::from_env();
+                assert_eq!(
+                    config.message_ttl_secs, expected,
+                    "Failed for {}: input '{}'",
+                    description, input
+                );
+            });
+        }
+    }
+
+    #[test]
+    fn test_dedup_windows() {
+        let test_cases = vec![
+            ("60", 60, "valid seconds"),
+            ("0", 0, "zero disables deduplication"),
+            ("abc", DEFAULT_DEDUP_WINDOW_SECS, "invalid string"),
+            ("-1", DEFAULT_DEDUP_WINDOW_SECS, "negative value"),
+        ];
+
+        for (input, expected, description) in test_cases {
+            with_env_var("TLQ_DEDUP_WINDOW", input, || {
+                let config = Config::from_env();
+                assert_eq!(
+                    config.dedup_window_secs, expected,
+                    "Failed for {}: input '{}'",
+                    description, input
+                );
+            });
+        }
+    }
+
+    #[test]
+    fn test_shutdown_graces() {
+        let test_cases = vec![
+            ("10", 10, "valid seconds"),
+            ("0", 0, "zero drops in-flight requests right away"),
+            ("abc", DEFAULT_SHUTDOWN_GRACE_SECS, "invalid string"),
+            ("-1", DEFAULT_SHUTDOWN_GRACE_SECS, "negative value"),
+        ];
+
+        for (input, expected, description) in test_cases {
+            with_env_var("TLQ_SHUTDOWN_GRACE", input, || {
+                let config = Config::from_env();
+                assert_eq!(
+                    config.shutdown_grace_secs, expected,
+                    "Failed for {}: input '{}'",
+                    description, input
+                );
+            });
+        }
+    }
+
     #[test]
     fn test_worker_interval_derived_from_lock_duration() {
         with_env_var("TLQ_LOCK_DURATION", "300", || {
@@ -349,6 +608,165 @@ mod tests {
         clear_env_vars();
     }
 
+    #[test]
+    fn test_storage_backends() {
+        let test_cases = vec![
+            ("memory", StorageBackend::Memory, "memory backend"),
+            ("log", StorageBackend::Log, "log backend"),
+            ("LOG", StorageBackend::Log, "uppercase"),
+            ("disk", StorageBackend::Memory, "unknown backend"),
+            ("", StorageBackend::Memory, "empty string"),
+        ];
+
+        for (input, expected, description) in test_cases {
+            with_env_var("TLQ_STORAGE", input, || {
+                let config = Config::from_env();
+                assert_eq!(
+                    config.storage, expected,
+                    "Failed for {}: input '{}'",
+                    description, input
+                );
+            });
+        }
+    }
+
+    #[test]
+    fn test_storage_path() {
+        with_env_var("TLQ_STORAGE_PATH", "/var/lib/tlq/queue.log", || {
+            let config = Config::from_env();
+            assert_eq!(config.storage_path, "/var/lib/tlq/queue.log");
+        });
+
+        with_env_var("TLQ_STORAGE_PATH", "", || {
+            let config = Config::from_env();
+            assert_eq!(config.storage_path, DEFAULT_STORAGE_PATH);
+        });
+    }
+
+    #[test]
+    fn test_storage_sync() {
+        let test_cases = vec![
+            ("always", StorageSync::Always, "always"),
+            ("never", StorageSync::Never, "never"),
+            ("NEVER", StorageSync::Never, "uppercase"),
+            ("sometimes", StorageSync::Always, "unknown policy"),
+            ("", StorageSync::Always, "empty string"),
+        ];
+
+        for (input, expected, description) in test_cases {
+            with_env_var("TLQ_STORAGE_SYNC", input, || {
+                let config = Config::from_env();
+                assert_eq!(
+                    config.storage_sync, expected,
+                    "Failed for {}: input '{}'",
+                    description, input
+                );
+            });
+        }
+    }
+
+    #[test]
+    fn test_snapshot_path() {
+        with_env_var("TLQ_SNAPSHOT_PATH", "/var/lib/tlq/snapshot.jsonl", || {
+            let config = Config::from_env();
+            assert_eq!(
+                config.snapshot_path.as_deref(),
+                Some("/var/lib/tlq/snapshot.jsonl")
+            );
+        });
+
+        with_env_var("TLQ_SNAPSHOT_PATH", "", || {
+            let config = Config::from_env();
+            assert_eq!(config.snapshot_path, None);
+        });
+    }
+
+    #[test]
+    fn test_snapshot_intervals() {
+        let test_cases = vec![
+            ("10", 10, "valid seconds"),
+            ("0", DEFAULT_SNAPSHOT_INTERVAL_SECS, "zero value"),
+            ("abc", DEFAULT_SNAPSHOT_INTERVAL_SECS, "invalid string"),
+            ("-1", DEFAULT_SNAPSHOT_INTERVAL_SECS, "negative value"),
+        ];
+
+        for (input, expected, description) in test_cases {
+            with_env_var("TLQ_SNAPSHOT_INTERVAL", input, || {
+                let config = Config::from_env();
+                assert_eq!(
+                    config.snapshot_interval_secs, expected,
+                    "Failed for {}: input '{}'",
+                    description, input
+                );
+            });
+        }
+    }
+
+    #[test]
+    fn test_max_snapshot_sizes() {
+        let test_cases = vec![
+            ("1048576", 1024 * 1024, "raw bytes"),
+            ("512K", 512 * 1024, "K suffix"),
+            ("256M", 256 * 1024 * 1024, "M suffix"),
+            ("0M", DEFAULT_MAX_SNAPSHOT_SIZE, "zero with M suffix"),
+            ("abc", DEFAULT_MAX_SNAPSHOT_SIZE, "invalid format"),
+            ("", DEFAULT_MAX_SNAPSHOT_SIZE, "empty string"),
+        ];
+
+        for (input, expected_size, description) in test_cases {
+            with_env_var("TLQ_MAX_SNAPSHOT_SIZE", input, || {
+                let config = Config::from_env();
+                assert_eq!(
+                    config.max_snapshot_size, expected_size,
+                    "Failed for {}: input '{}'",
+                    description, input
+                );
+            });
+        }
+    }
+
+    #[test]
+    fn test_ignored_options() {
+        let _lock = TEST_MUTEX.lock().unwrap();
+        clear_env_vars();
+        env::set_var("TLQ_STORAGE", "log");
+        env::set_var("TLQ_SNAPSHOT_PATH", "/var/lib/tlq/snapshot.jsonl");
+        env::set_var("TLQ_SNAPSHOT_INTERVAL", "30");
+        env::set_var("TLQ_STORAGE_PATH", "/var/lib/tlq/queue.log");
+        let config = Config::from_env();
+        assert_eq!(
+            config.ignored_options,
+            vec!["TLQ_SNAPSHOT_PATH", "TLQ_SNAPSHOT_INTERVAL"]
+        );
+
+        env::set_var("TLQ_STORAGE", "memory");
+        let config = Config::from_env();
+        assert_eq!(config.ignored_options, vec!["TLQ_STORAGE_PATH"]);
+
+        clear_env_vars();
+    }
+
+    #[test]
+    fn test_queue_defaults() {
+        let _lock = TEST_MUTEX.lock().unwrap();
+        clear_env_vars();
+        env::set_var("TLQ_LOCK_DURATION", "30");
+        env::set_var("TLQ_MAX_RETRIES", "7");
+        env::set_var("TLQ_MAX_MESSAGE_SIZE", "1K");
+        env::set_var("TLQ_MESSAGE_TTL", "600");
+        env::set_var("TLQ_DEDUP_WINDOW", "120");
+
+        let defaults = Config::from_env().queue_defaults();
+        assert_eq!(defaults.lock_duration_secs, 30);
+        assert_eq!(defaults.max_retries, 7);
+        assert_eq!(defaults.max_message_size, 1024);
+        assert_eq!(defaults.ttl_secs, 600);
+        assert!(!defaults.dead_letter_expired);
+        assert_eq!(defaults.dedup_window_secs, 120);
+
+        clear_env_vars();
+    }
+
     #[test]
     fn test_parse_size_helper() {
         // Valid cases
@@ -356,12 +774,15 @@ mod tests {
         assert_eq!(Config::parse_size("64K"), Some(65536));
         assert_eq!(Config::parse_size("64k"), Some(65536));
         assert_eq!(Config::parse_size("1K"), Some(1024));
+        assert_eq!(Config::parse_size("2M"), Some(2 * 1024 * 1024));
+        assert_eq!(Config::parse_size("2m"), Some(2 * 1024 * 1024));
 
         // Invalid cases
         assert_eq!(Config::parse_size(""), None);
         assert_eq!(Config::parse_size("0"), None);
         assert_eq!(Config::parse_size("0K"), None);
         assert_eq!(Config::parse_size("K"), None);
+        assert_eq!(Config::parse_size("M"), None);
         assert_eq!(Config::parse_size("abc"), None);
         assert_eq!(Config::parse_size("-1"), None);
     }
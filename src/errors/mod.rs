@@ -0,0 +1,52 @@
+use std::fmt;
+
+/// Errors returned by the storage and service layers
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// The request is malformed or violates a limit
+    Validation(String),
+    /// The addressed queue or message does not exist
+    NotFound(String),
+    /// The request conflicts with the current state, e.g. a queue that already exists
+    Conflict(String),
+    /// The message body exceeds the queue's size limit
+    PayloadTooLarge(String),
+    /// The storage backend failed, e.g. the log could not be written
+    Storage(String),
+    /// The server cannot take the request right now
+    Unavailable(String),
+}
+
+impl Error {
+    /// The human-readable description of the error
+    pub fn message(&self) -> &str {
+        match self {
+            Error::Validation(message)
+            | Error::NotFound(message)
+            | Error::Conflict(message)
+            | Error::PayloadTooLarge(message)
+            | Error::Storage(message)
+            | Error::Unavailable(message) => message,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.message())
+    }
+}
+
+impl std::error::Error for Error {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display_shows_message() {
+        let error = Error::NotFound("Queue 'orders' not found".to_string());
+        assert_eq!(error.to_string(), "Queue 'orders' not found");
+        assert_eq!(error.message(), "Queue 'orders' not found");
+    }
+}
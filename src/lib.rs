@@ -1,6 +1,10 @@
 pub mod api;
 pub mod config;
+pub mod errors;
+pub mod metrics;
 pub mod services;
+pub mod snapshot;
 pub mod storage;
+mod time;
 pub mod types;
 pub mod worker;
@@ -1,10 +1,14 @@
+use std::future::IntoFuture;
 use std::sync::Arc;
+use std::time::Duration;
 use tlq::api::create_api;
-use tlq::config::config;
+use tlq::config::{config, StorageBackend};
 use tlq::services::MessageService;
+use tlq::storage::log::LogStorage;
 use tlq::storage::memory::MemoryStorage;
 use tlq::storage::traits::Storage;
-use tracing::info;
+use tokio::sync::watch;
+use tracing::{info, warn};
 use tracing_subscriber::{
     filter::LevelFilter, layer::Layer, layer::SubscriberExt, util::SubscriberInitExt,
 };
@@ -21,25 +25,115 @@ async fn main() {
         .init();
 
     info!(
-        "Starting TLQ with configuration: port={}, max_message_size={}, log_level={}, lock_duration={}s, max_retries={}",
-        cfg.port, cfg.max_message_size, cfg.log_level, cfg.lock_duration_secs, cfg.max_retries
+        "Starting TLQ with configuration: port={}, max_message_size={}, log_level={}, lock_duration={}s, max_retries={}, storage={:?}",
+        cfg.port, cfg.max_message_size, cfg.log_level, cfg.lock_duration_secs, cfg.max_retries, cfg.storage
     );
+    for option in &cfg.ignored_options {
+        warn!(
+            "{} has no effect with the {:?} storage backend",
+            option, cfg.storage
+        );
+    }
 
-    let store = Arc::new(MemoryStorage::new());
-    let reaper_store: Arc<dyn Storage> = store.clone();
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+    let mut snapshots = None;
+    let store: Arc<dyn Storage> = match (cfg.storage, &cfg.snapshot_path) {
+        (StorageBackend::Memory, Some(path)) => {
+            let storage = Arc::new(
+                MemoryStorage::open(path)
+                    .await
+                    .expect("Failed to load snapshot"),
+            );
+            snapshots = Some(tokio::spawn(tlq::worker::start_snapshots(
+                storage.clone(),
+                cfg.snapshot_interval_secs,
+                shutdown_rx.clone(),
+            )));
+            storage
+        }
+        (StorageBackend::Memory, None) => Arc::new(MemoryStorage::new()),
+        (StorageBackend::Log, _) => Arc::new(
+            LogStorage::open(&cfg.storage_path)
+                .await
+                .expect("Failed to open storage log"),
+        ),
+    };
+    let reaper_store = store.clone();
     let service = MessageService::new(store);
 
-    tokio::spawn(tlq::worker::start_reaper(
+    let reaper = tokio::spawn(tlq::worker::start_reaper(
         reaper_store,
-        cfg.max_retries,
         cfg.worker_interval_secs,
+        shutdown_rx.clone(),
     ));
 
-    let app = create_api(service);
+    let app = create_api(service.clone());
     let bind_addr = format!("[::]:{}", cfg.port);
     let listener = tokio::net::TcpListener::bind(bind_addr).await.unwrap();
 
     info!("Listening on {}", listener.local_addr().unwrap());
 
-    axum::serve(listener, app).await.unwrap();
+    let signal_service = service.clone();
+    tokio::spawn(async move {
+        shutdown_signal().await;
+        info!("Shutting down, draining in-flight requests");
+        signal_service.shutdown();
+        shutdown_tx.send_replace(true);
+    });
+
+    let server = axum::serve(listener, app).with_graceful_shutdown(stopped(shutdown_rx.clone()));
+    let grace = Duration::from_secs(cfg.shutdown_grace_secs);
+    let grace_elapsed = async move {
+        stopped(shutdown_rx).await;
+        tokio::time::sleep(grace).await;
+    };
+    tokio::select! {
+        result = server.into_future() => result.unwrap(),
+        _ = grace_elapsed => warn!(
+            "In-flight requests not finished after {}s, dropping them",
+            cfg.shutdown_grace_secs
+        ),
+    }
+
+    if let Err(e) = reaper.await {
+        warn!("Reaper failed: {}", e);
+    }
+    if let Some(snapshots) = snapshots {
+        if let Err(e) = snapshots.await {
+            warn!("Snapshots failed: {}", e);
+        }
+    }
+    if let Err(e) = service.flush().await {
+        warn!("Failed to flush storage: {}", e);
+    }
+    info!("Shutdown complete");
+}
+
+/// Resolves once `shutdown` turns true.
+async fn stopped(mut shutdown: watch::Receiver<bool>) {
+    let _ = shutdown.wait_for(|stopping| *stopping).await;
+}
+
+/// Resolves on SIGINT (Ctrl+C) or SIGTERM.
+async fn shutdown_signal() {
+    let interrupt = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("Failed to listen for SIGINT");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("Failed to listen for SIGTERM")
+            .recv()
+            .await;
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = interrupt => {}
+        _ = terminate => {}
+    }
 }
@@ -0,0 +1,201 @@
+use crate::types::{Message, QueueInfo, ReceiptOutcome, ReceiptStatus};
+use prometheus::{
+    exponential_buckets, Encoder, HistogramOpts, HistogramVec, IntCounter, IntCounterVec,
+    IntGaugeVec, Opts, Registry, TextEncoder,
+};
+use std::sync::OnceLock;
+
+/// Prometheus metrics collected by the server and exposed on `/metrics`
+pub struct Metrics {
+    registry: Registry,
+    /// Messages available for processing, per queue
+    pub ready: IntGaugeVec,
+    /// Messages currently being processed, per queue
+    pub processing: IntGaugeVec,
+    /// Messages in the dead-letter queue, per queue
+    pub dead: IntGaugeVec,
+    /// Messages added, per queue; duplicate adds are not counted
+    pub added: IntCounterVec,
+    /// Messages handed out by `/get`, per queue
+    pub delivered: IntCounterVec,
+    /// Messages deleted by consumers, per queue
+    pub deleted: IntCounterVec,
+    /// Messages returned to the queue by consumers, per queue
+    pub retried: IntCounterVec,
+    /// Purges, per queue
+    pub purges: IntCounterVec,
+    /// Messages whose lock expired and that the reaper returned to their queue
+    pub reaper_retried: IntCounter,
+    /// Messages whose lock expired and that the reaper moved to the dead-letter queue
+    pub reaper_dead: IntCounter,
+    /// Size of added messages in bytes, per queue
+    pub message_size: HistogramVec,
+    /// HTTP request latency in seconds, per method, route and status
+    pub http_duration: HistogramVec,
+}
+
+/// Returns the process-wide metrics, registering them on first use.
+pub fn metrics() -> &'static Metrics {
+    static METRICS: OnceLock<Metrics> = OnceLock::new();
+    METRICS.get_or_init(Metrics::new)
+}
+
+impl Metrics {
+    fn new() -> Self {
+        let registry = Registry::new();
+        let queue_gauge = |name: &str, help: &str| {
+            let gauge = IntGaugeVec::new(Opts::new(name, help), &["queue"]).unwrap();
+            registry.register(Box::new(gauge.clone())).unwrap();
+            gauge
+        };
+        let queue_counter = |name: &str, help: &str| {
+            let counter = IntCounterVec::new(Opts::new(name, help), &["queue"]).unwrap();
+            registry.register(Box::new(counter.clone())).unwrap();
+            counter
+        };
+        let counter = |name: &str, help: &str| {
+            let counter = IntCounter::new(name, help).unwrap();
+            registry.register(Box::new(counter.clone())).unwrap();
+            counter
+        };
+        let histogram = |opts: HistogramOpts, labels: &[&str]| {
+            let histogram = HistogramVec::new(opts, labels).unwrap();
+            registry.register(Box::new(histogram.clone())).unwrap();
+            histogram
+        };
+
+        Self {
+            ready: queue_gauge("tlq_ready_messages", "Messages available for processing"),
+            processing: queue_gauge(
+                "tlq_processing_messages",
+                "Messages currently being processed",
+            ),
+            dead: queue_gauge("tlq_dead_messages", "Messages in the dead-letter queue"),
+            added: queue_counter("tlq_messages_added_total", "Messages added"),
+            delivered: queue_counter("tlq_messages_delivered_total", "Messages handed out"),
+            deleted: queue_counter("tlq_messages_deleted_total", "Messages deleted"),
+            retried: queue_counter("tlq_messages_retried_total", "Messages retried"),
+            purges: queue_counter("tlq_purges_total", "Queue purges"),
+            reaper_retried: counter(
+                "tlq_reaper_retried_total",
+                "Expired locks returned to their queue by the reaper",
+            ),
+            reaper_dead: counter(
+                "tlq_reaper_dead_total",
+                "Expired locks moved to the dead-letter queue by the reaper",
+            ),
+            message_size: histogram(
+                HistogramOpts::new("tlq_message_size_bytes", "Size of added messages")
+                    .buckets(exponential_buckets(64.0, 4.0, 8).unwrap()),
+                &["queue"],
+            ),
+            http_duration: histogram(
+                HistogramOpts::new("tlq_http_request_duration_seconds", "HTTP request latency"),
+                &["method", "route", "status"],
+            ),
+            registry,
+        }
+    }
+
+    /// Records a message added to `queue`.
+    pub fn record_added(&self, queue: &str, message: &Message) {
+        self.added.with_label_values(&[queue]).inc();
+        self.message_size
+            .with_label_values(&[queue])
+            .observe(message.size() as f64);
+    }
+
+    /// Records the messages of `queue` deleted or retried by a consumer request.
+    pub fn record_outcomes(&self, queue: &str, outcomes: &[ReceiptOutcome]) {
+        for outcome in outcomes {
+            match outcome.status {
+                ReceiptStatus::Deleted => self.deleted.with_label_values(&[queue]).inc(),
+                ReceiptStatus::Retried => self.retried.with_label_values(&[queue]).inc(),
+                _ => {}
+            }
+        }
+    }
+
+    /// Sets the queue gauges from the current statistics of every queue,
+    /// dropping the series of queues that no longer exist.
+    pub fn set_queue_stats(&self, queues: &[QueueInfo]) {
+        for gauge in [&self.ready, &self.processing, &self.dead] {
+            gauge.reset();
+        }
+        for queue in queues {
+            let labels = [queue.name.as_str()];
+            self.ready
+                .with_label_values(&labels)
+                .set(queue.stats.ready as i64);
+            self.processing
+                .with_label_values(&labels)
+                .set(queue.stats.processing as i64);
+            self.dead
+                .with_label_values(&labels)
+                .set(queue.stats.dead as i64);
+        }
+    }
+
+    /// Renders all metrics in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let mut buffer = Vec::new();
+        TextEncoder::new()
+            .encode(&self.registry.gather(), &mut buffer)
+            .expect("Failed to encode metrics");
+        String::from_utf8(buffer).expect("Metrics are not valid UTF-8")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::QueueStats;
+
+    fn queue_info(name: &str, ready: usize) -> QueueInfo {
+        QueueInfo {
+            name: name.to_string(),
+            stats: QueueStats {
+                ready,
+                processing: 0,
+                scheduled: 0,
+                dead: 0,
+                expired: 0,
+                oldest_ready_age_ms: None,
+                oldest_lock_until: None,
+                bytes: 0,
+                enqueued: 0,
+                acked: 0,
+                retried: 0,
+                enqueue_rate: 0.0,
+                ack_rate: 0.0,
+            },
+            config: crate::config::config().queue_defaults(),
+        }
+    }
+
+    #[test]
+    fn test_render_includes_recorded_metrics() {
+        let metrics = Metrics::new();
+        metrics.record_added("orders", &Message::new("Hello".to_string()));
+        metrics.reaper_dead.inc_by(2);
+
+        let output = metrics.render();
+        assert!(output.contains("tlq_messages_added_total{queue=\"orders\"} 1"));
+        assert!(output.contains("tlq_message_size_bytes_count{queue=\"orders\"} 1"));
+        assert!(output.contains("tlq_reaper_dead_total 2"));
+    }
+
+    #[test]
+    fn test_set_queue_stats_drops_deleted_queues() {
+        let metrics = Metrics::new();
+        metrics.set_queue_stats(&[queue_info("default", 3), queue_info("orders", 1)]);
+        assert!(metrics
+            .render()
+            .contains("tlq_ready_messages{queue=\"orders\"} 1"));
+
+        metrics.set_queue_stats(&[queue_info("default", 2)]);
+        let output = metrics.render();
+        assert!(output.contains("tlq_ready_messages{queue=\"default\"} 2"));
+        assert!(!output.contains("queue=\"orders\""));
+    }
+}
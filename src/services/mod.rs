@@ -1,57 +1,558 @@
 use crate::config;
+use crate::errors::Error;
+use crate::metrics::metrics;
+use crate::snapshot;
 use crate::storage::traits::Storage;
-use crate::types::{Message, QueueStats};
+use crate::time::now_millis;
+use crate::types::{
+    AddOptions, AddOutcome, BodyEncoding, DeadLetter, ImportSummary, Message, MessagePage,
+    MessageState, ProcessingRestore, QueueConfig, QueueConfigUpdate, QueueInfo, QueueStats,
+    ReceiptOutcome, RedriveOutcome, DEFAULT_QUEUE,
+};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use std::collections::BTreeMap;
 use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio::time::{timeout_at, Instant};
+use uuid::Uuid;
+
+const MAX_QUEUE_NAME_LENGTH: usize = 80;
+const MAX_WAIT_SECS: u64 = 20;
+const MAX_KEY_LENGTH: usize = 128;
+const MAX_BROWSE_LIMIT: usize = 1000;
+const MAX_DEDUP_WINDOW_SECS: u64 = 604800; // 7 days
 
 #[derive(Clone)]
 pub struct MessageService {
     store: Arc<dyn Storage>,
+    /// Set once the server starts shutting down
+    shutdown: watch::Sender<bool>,
 }
 
 impl MessageService {
     pub fn new(store: Arc<dyn Storage>) -> MessageService {
-        Self { store }
+        let (shutdown, _) = watch::channel(false);
+        Self { store, shutdown }
+    }
+
+    /// Starts shutting down: waiting long polls return right away with what they have.
+    pub fn shutdown(&self) {
+        self.shutdown.send_replace(true);
+    }
+
+    /// Rejects new messages and deliveries once the server is shutting down, so
+    /// nothing is accepted after the final snapshot or flush.
+    fn check_running(&self) -> Result<(), Error> {
+        if *self.shutdown.borrow() {
+            return Err(Error::Unavailable("Server is shutting down".to_string()));
+        }
+        Ok(())
+    }
+
+    /// Makes every change so far durable. Call once in-flight requests are drained.
+    pub async fn flush(&self) -> Result<(), Error> {
+        self.store.flush().await
     }
 }
 
 impl MessageService {
-    pub async fn add(&self, body: String) -> Result<Message, String> {
-        if body.len() > config::config().max_message_size {
-            return Err("Message body size is too large".to_string());
+    pub async fn create_queue(
+        &self,
+        queue: &str,
+        overrides: QueueConfigUpdate,
+    ) -> Result<QueueConfig, Error> {
+        Self::validate_queue_name(queue)?;
+        Self::validate_queue_config(&overrides)?;
+
+        let config = config::config().queue_defaults().merge(&overrides);
+        self.store.create_queue(queue, config).await?;
+        Ok(config)
+    }
+
+    pub async fn delete_queue(&self, queue: &str) -> Result<(), Error> {
+        if queue == DEFAULT_QUEUE {
+            return Err(Error::Validation(
+                "The default queue cannot be deleted".to_string(),
+            ));
+        }
+
+        self.store.delete_queue(queue).await
+    }
+
+    pub async fn list_queues(&self) -> Result<Vec<QueueInfo>, Error> {
+        self.store.list_queues().await
+    }
+
+    pub async fn queue_config(&self, queue: &str) -> Result<Option<QueueConfig>, Error> {
+        self.store.queue_config(queue).await
+    }
+
+    pub async fn update_queue_config(
+        &self,
+        queue: &str,
+        update: QueueConfigUpdate,
+    ) -> Result<QueueConfig, Error> {
+        Self::validate_queue_config(&update)?;
+
+        self.store.update_queue_config(queue, update).await
+    }
+
+    pub async fn add(
+        &self,
+        queue: &str,
+        body: String,
+        options: AddOptions,
+    ) -> Result<Message, Error> {
+        self.check_running()?;
+        Self::validate_queue_name(queue)?;
+        let queue_config = self.add_config(queue).await?;
+
+        let msg = Self::prepare(&queue_config, body, options, now_millis())?;
+        let id = msg.id;
+        let added = self.store.add(queue, msg).await?;
+        // A duplicate add returns the original message and enqueues nothing
+        if added.id == id {
+            metrics().record_added(queue, &added);
+        }
+        Ok(added)
+    }
+
+    /// Adds several messages at once. Each message is validated on its own; a
+    /// rejected message is reported in its outcome and does not affect the others.
+    pub async fn add_batch(
+        &self,
+        queue: &str,
+        items: Vec<(String, AddOptions)>,
+    ) -> Result<Vec<AddOutcome>, Error> {
+        self.check_running()?;
+        Self::validate_queue_name(queue)?;
+        if items.is_empty() {
+            return Err(Error::Validation("No messages provided".to_string()));
+        }
+        let queue_config = self.add_config(queue).await?;
+
+        let now = now_millis();
+        let mut rejections = Vec::with_capacity(items.len());
+        let mut msgs = Vec::with_capacity(items.len());
+        for (body, options) in items {
+            match Self::prepare(&queue_config, body, options, now) {
+                Ok(msg) => {
+                    msgs.push(msg);
+                    rejections.push(None);
+                }
+                Err(err) => rejections.push(Some(err)),
+            }
+        }
+
+        // A batch without valid messages must not create the queue
+        let ids: Vec<Uuid> = msgs.iter().map(|msg| msg.id).collect();
+        let added = if msgs.is_empty() {
+            Vec::new()
+        } else {
+            self.store.add_batch(queue, msgs).await?
+        };
+        for (msg, id) in added.iter().zip(ids) {
+            if msg.id == id {
+                metrics().record_added(queue, msg);
+            }
+        }
+        let mut added = added.into_iter();
+
+        Ok(rejections
+            .into_iter()
+            .map(|rejection| match rejection {
+                Some(err) => AddOutcome {
+                    message: None,
+                    error: Some(err.to_string()),
+                },
+                None => AddOutcome {
+                    message: added.next(),
+                    error: None,
+                },
+            })
+            .collect())
+    }
+
+    /// The configuration messages added to `queue` are checked against. Queues
+    /// that do not exist yet are created with the default settings.
+    async fn add_config(&self, queue: &str) -> Result<QueueConfig, Error> {
+        Ok(match self.store.queue_config(queue).await? {
+            Some(queue_config) => queue_config,
+            None => config::config().queue_defaults(),
+        })
+    }
+
+    /// Validates a message to be added and builds it.
+    fn prepare(
+        queue_config: &QueueConfig,
+        body: String,
+        options: AddOptions,
+        now: i64,
+    ) -> Result<Message, Error> {
+        let deliver_at = Self::delivery_time(&options, now)?;
+        let expires_at = Self::expiry_time(&options, queue_config, deliver_at.unwrap_or(now))?;
+        Self::validate_attributes(&options.attributes)?;
+        Self::validate_key("dedup_id", options.dedup_id.as_deref())?;
+        Self::validate_key("group_id", options.group_id.as_deref())?;
+        Self::validate_group_order(&options)?;
+        Self::validate_body(&body, options.encoding)?;
+
+        let mut msg = Message::new(body);
+        msg.encoding = options.encoding;
+        msg.attributes = options.attributes;
+        msg.dedup_id = options.dedup_id;
+        msg.group_id = options.group_id;
+        if msg.size() > queue_config.max_message_size {
+            return Err(Error::PayloadTooLarge(
+                "Message body size is too large".to_string(),
+            ));
         }
 
-        let msg = Message::new(body);
-        self.store.add(msg.clone()).await?;
+        msg.priority = options.priority.unwrap_or_default();
+        msg.expires_at = expires_at;
+        if let Some(deliver_at) = deliver_at {
+            msg.schedule(deliver_at, now);
+        }
         Ok(msg)
     }
 
-    pub async fn get(&self, count: usize) -> Result<Vec<Message>, String> {
-        self.store.get(count).await
+    /// Retrieves up to `count` messages. With `wait_secs`, an empty queue parks
+    /// the request until messages become ready or the wait time elapses.
+    pub async fn get(
+        &self,
+        queue: &str,
+        count: usize,
+        lock_secs: Option<u64>,
+        wait_secs: Option<u64>,
+    ) -> Result<Vec<Message>, Error> {
+        self.check_running()?;
+        if let Some(lock_secs) = lock_secs {
+            let max_lock_secs = config::config().max_lock_duration_secs;
+            if lock_secs == 0 || lock_secs > max_lock_secs {
+                return Err(Error::Validation(format!(
+                    "lock_secs must be between 1 and {max_lock_secs}"
+                )));
+            }
+        }
+
+        let wait_secs = wait_secs.unwrap_or(0);
+        if wait_secs > MAX_WAIT_SECS {
+            return Err(Error::Validation(format!(
+                "wait_secs must be at most {MAX_WAIT_SECS}"
+            )));
+        }
+
+        let messages = self.receive(queue, count, lock_secs, wait_secs).await?;
+        metrics()
+            .delivered
+            .with_label_values(&[queue])
+            .inc_by(messages.len() as u64);
+        Ok(messages)
     }
 
-    pub async fn stats(&self) -> Result<QueueStats, String> {
-        self.store.stats().await
+    /// Retrieves up to `count` messages, waiting up to `wait_secs` for them.
+    async fn receive(
+        &self,
+        queue: &str,
+        count: usize,
+        lock_secs: Option<u64>,
+        wait_secs: u64,
+    ) -> Result<Vec<Message>, Error> {
+        let messages = self.store.get(queue, count, lock_secs).await?;
+        if !messages.is_empty() || count == 0 || wait_secs == 0 {
+            return Ok(messages);
+        }
+
+        let deadline = Instant::now() + Duration::from_secs(wait_secs);
+        let notify = self.store.subscribe(queue);
+        let mut shutdown = self.shutdown.subscribe();
+        loop {
+            let notified = notify.notified();
+            tokio::pin!(notified);
+            // Register before checking again so a message added in between is not missed
+            notified.as_mut().enable();
+
+            let messages = self.store.get(queue, count, lock_secs).await?;
+            if !messages.is_empty() {
+                return Ok(messages);
+            }
+
+            tokio::select! {
+                woken = timeout_at(deadline, notified) => {
+                    if woken.is_err() {
+                        return Ok(messages);
+                    }
+                }
+                // Release the request so the server can drain
+                _ = shutdown.wait_for(|stopping| *stopping) => return Ok(messages),
+            }
+        }
     }
 
-    pub async fn delete(&self, ids: Vec<String>) -> Result<(), String> {
-        Self::validate_ids(&ids)?;
+    pub async fn extend(
+        &self,
+        queue: &str,
+        receipts: Vec<String>,
+        extend_secs: u64,
+    ) -> Result<Vec<Message>, Error> {
+        Self::validate_receipts(&receipts)?;
+        let max_lock_secs = config::config().max_lock_duration_secs;
+        if extend_secs == 0 || extend_secs > max_lock_secs {
+            return Err(Error::Validation(format!(
+                "extend_secs must be between 1 and {max_lock_secs}"
+            )));
+        }
 
-        self.store.delete(ids).await
+        self.store.extend(queue, receipts, extend_secs).await
     }
 
-    pub async fn purge(&self) -> Result<(), String> {
-        self.store.purge().await
+    pub async fn stats(&self, queue: &str) -> Result<QueueStats, Error> {
+        self.store.stats(queue).await
     }
 
-    pub async fn retry(&self, ids: Vec<String>) -> Result<(), String> {
+    pub async fn delete(
+        &self,
+        queue: &str,
+        receipts: Vec<String>,
+    ) -> Result<Vec<ReceiptOutcome>, Error> {
+        Self::validate_receipts(&receipts)?;
+
+        let outcomes = self.store.delete(queue, receipts).await?;
+        metrics().record_outcomes(queue, &outcomes);
+        Ok(outcomes)
+    }
+
+    pub async fn purge(&self, queue: &str) -> Result<(), Error> {
+        self.store.purge(queue).await?;
+        metrics().purges.with_label_values(&[queue]).inc();
+        Ok(())
+    }
+
+    pub async fn retry(
+        &self,
+        queue: &str,
+        receipts: Vec<String>,
+    ) -> Result<Vec<ReceiptOutcome>, Error> {
+        Self::validate_receipts(&receipts)?;
+
+        let outcomes = self.store.retry(queue, receipts).await?;
+        metrics().record_outcomes(queue, &outcomes);
+        Ok(outcomes)
+    }
+
+    /// Lists messages in ID order without locking them or changing their state.
+    pub async fn browse(
+        &self,
+        queue: &str,
+        state: Option<MessageState>,
+        after: Option<Uuid>,
+        limit: usize,
+    ) -> Result<MessagePage, Error> {
+        if limit == 0 || limit > MAX_BROWSE_LIMIT {
+            return Err(Error::Validation(format!(
+                "limit must be between 1 and {MAX_BROWSE_LIMIT}"
+            )));
+        }
+
+        // One extra message tells whether there is a next page
+        let mut messages = self.store.browse(queue, state, after, limit + 1).await?;
+        let next_cursor = if messages.len() > limit {
+            messages.truncate(limit);
+            messages.last().map(|message| message.id)
+        } else {
+            None
+        };
+
+        Ok(MessagePage {
+            messages,
+            next_cursor,
+        })
+    }
+
+    pub async fn list_dead(&self, queue: &str, limit: usize) -> Result<Vec<DeadLetter>, Error> {
+        self.store.list_dead(queue, limit).await
+    }
+
+    pub async fn find(&self, queue: &str, id: String) -> Result<Option<Message>, Error> {
+        Self::validate_ids(&vec![id.clone()])?;
+
+        self.store.find(queue, id).await
+    }
+
+    pub async fn get_dead(&self, queue: &str, id: String) -> Result<Option<DeadLetter>, Error> {
+        Self::validate_ids(&vec![id.clone()])?;
+
+        self.store.get_dead(queue, id).await
+    }
+
+    pub async fn redrive(
+        &self,
+        queue: &str,
+        ids: Vec<String>,
+    ) -> Result<Vec<RedriveOutcome>, Error> {
         Self::validate_ids(&ids)?;
 
-        self.store.retry(ids).await
+        self.store.redrive(queue, ids).await
+    }
+
+    pub async fn purge_dead(&self, queue: &str) -> Result<(), Error> {
+        self.store.purge_dead(queue).await
     }
 
-    fn validate_ids(ids: &Vec<String>) -> Result<(), String> {
+    /// Writes every message and dead letter of a queue as a JSON Lines snapshot.
+    pub async fn export(&self, queue: &str) -> Result<String, Error> {
+        let snapshot = self.store.export(queue).await?;
+        snapshot::encode(queue, snapshot, now_millis())
+    }
+
+    /// Restores a snapshot written by [`MessageService::export`] into a queue,
+    /// creating the queue if it does not exist.
+    pub async fn import(
+        &self,
+        queue: &str,
+        input: &str,
+        processing: ProcessingRestore,
+    ) -> Result<ImportSummary, Error> {
+        self.check_running()?;
+        Self::validate_queue_name(queue)?;
+        let snapshot = snapshot::decode(input)?;
+
+        self.store.import(queue, snapshot, processing).await
+    }
+
+    fn validate_queue_name(queue: &str) -> Result<(), Error> {
+        let valid = !queue.is_empty()
+            && queue.len() <= MAX_QUEUE_NAME_LENGTH
+            && queue
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+
+        if !valid {
+            return Err(Error::Validation(format!(
+                "Invalid queue name: {queue:?}. Use 1-{MAX_QUEUE_NAME_LENGTH} letters, digits, '-' or '_'"
+            )));
+        }
+
+        Ok(())
+    }
+
+    fn validate_queue_config(update: &QueueConfigUpdate) -> Result<(), Error> {
+        let max_lock_secs = config::config().max_lock_duration_secs;
+        if update
+            .lock_duration_secs
+            .is_some_and(|secs| secs == 0 || secs > max_lock_secs)
+        {
+            return Err(Error::Validation(format!(
+                "lock_duration_secs must be between 1 and {max_lock_secs}"
+            )));
+        }
+
+        if update
+            .dedup_window_secs
+            .is_some_and(|secs| secs > MAX_DEDUP_WINDOW_SECS)
+        {
+            return Err(Error::Validation(format!(
+                "dedup_window_secs must be at most {MAX_DEDUP_WINDOW_SECS}"
+            )));
+        }
+
+        if update.max_message_size == Some(0) {
+            return Err(Error::Validation(
+                "max_message_size must be greater than 0".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn validate_body(body: &str, encoding: BodyEncoding) -> Result<(), Error> {
+        if encoding == BodyEncoding::Base64 && BASE64.decode(body).is_err() {
+            return Err(Error::Validation("Body is not valid base64".to_string()));
+        }
+
+        Ok(())
+    }
+
+    fn validate_key(field: &str, key: Option<&str>) -> Result<(), Error> {
+        match key {
+            Some(key) if key.is_empty() || key.len() > MAX_KEY_LENGTH => Err(Error::Validation(
+                format!("{field} must be 1-{MAX_KEY_LENGTH} bytes long"),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Grouped messages are delivered in the order they were added, so they
+    /// cannot be reordered by a priority or a delivery time.
+    fn validate_group_order(options: &AddOptions) -> Result<(), Error> {
+        let reordered = options.priority.is_some_and(|priority| priority != 0)
+            || options.delay_secs.is_some()
+            || options.deliver_at.is_some();
+        if options.group_id.is_some() && reordered {
+            return Err(Error::Validation(
+                "group_id cannot be combined with priority, delay_secs or deliver_at".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    fn validate_attributes(attributes: &BTreeMap<String, String>) -> Result<(), Error> {
+        if attributes.keys().any(|name| name.is_empty()) {
+            return Err(Error::Validation(
+                "Attribute names must not be empty".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Resolves `delay_secs` or `deliver_at` into an absolute delivery time in milliseconds.
+    fn delivery_time(options: &AddOptions, now: i64) -> Result<Option<i64>, Error> {
+        match (options.delay_secs, options.deliver_at) {
+            (Some(_), Some(_)) => Err(Error::Validation(
+                "Only one of delay_secs and deliver_at can be set".to_string(),
+            )),
+            (Some(delay_secs), None) => i64::try_from(delay_secs)
+                .ok()
+                .and_then(|secs| secs.checked_mul(1000))
+                .and_then(|millis| now.checked_add(millis))
+                .map(Some)
+                .ok_or_else(|| Error::Validation("delay_secs is too large".to_string())),
+            (None, deliver_at) => Ok(deliver_at),
+        }
+    }
+
+    /// Resolves the message time-to-live, counted from the time the message becomes
+    /// deliverable, into an absolute expiry time in milliseconds.
+    fn expiry_time(
+        options: &AddOptions,
+        queue_config: &QueueConfig,
+        deliverable_at: i64,
+    ) -> Result<Option<i64>, Error> {
+        let ttl_secs = match options.ttl_secs {
+            Some(0) => {
+                return Err(Error::Validation(
+                    "ttl_secs must be greater than 0".to_string(),
+                ))
+            }
+            Some(ttl_secs) => ttl_secs,
+            None if queue_config.ttl_secs > 0 => queue_config.ttl_secs,
+            None => return Ok(None),
+        };
+
+        i64::try_from(ttl_secs)
+            .ok()
+            .and_then(|secs| secs.checked_mul(1000))
+            .and_then(|millis| deliverable_at.checked_add(millis))
+            .map(Some)
+            .ok_or_else(|| Error::Validation("ttl_secs is too large".to_string()))
+    }
+
+    fn validate_ids(ids: &Vec<String>) -> Result<(), Error> {
         if ids.is_empty() {
-            return Err("No message IDs provided".to_string());
+            return Err(Error::Validation("No message IDs provided".to_string()));
         }
 
         let mut invalid_ids = Vec::new();
@@ -63,7 +564,26 @@ impl MessageService {
         }
 
         if !invalid_ids.is_empty() {
-            return Err(format!("Invalid message IDs: {invalid_ids:?}"));
+            return Err(Error::Validation(format!(
+                "Invalid message IDs: {invalid_ids:?}"
+            )));
+        }
+
+        Ok(())
+    }
+
+    fn validate_receipts(receipts: &[String]) -> Result<(), Error> {
+        if receipts.is_empty() {
+            return Err(Error::Validation("No receipts provided".to_string()));
+        }
+
+        let invalid: Vec<&String> = receipts
+            .iter()
+            .filter(|receipt| Message::receipt_id(receipt).is_none())
+            .collect();
+
+        if !invalid.is_empty() {
+            return Err(Error::Validation(format!("Invalid receipts: {invalid:?}")));
         }
 
         Ok(())
@@ -74,6 +594,7 @@ impl MessageService {
 mod tests {
     use super::*;
     use crate::storage::memory::MemoryStorage;
+    use crate::types::MessageState;
     use uuid::Uuid;
 
     #[tokio::test]
@@ -82,7 +603,9 @@ mod tests {
         let service = MessageService::new(store);
 
         let body = "A".repeat(config::config().max_message_size);
-        let result = service.add(body).await;
+        let result = service
+            .add(DEFAULT_QUEUE, body, AddOptions::default())
+            .await;
         assert!(result.is_ok());
     }
 
@@ -92,9 +615,666 @@ mod tests {
         let service = MessageService::new(store);
 
         let body = "A".repeat(config::config().max_message_size + 1);
-        let result = service.add(body).await;
+        let result = service
+            .add(DEFAULT_QUEUE, body, AddOptions::default())
+            .await;
         assert!(result.is_err());
-        assert_eq!(result.unwrap_err(), "Message body size is too large");
+        assert_eq!(
+            result.unwrap_err(),
+            Error::PayloadTooLarge("Message body size is too large".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_message_size_counts_attributes() {
+        let store = Arc::new(MemoryStorage::new());
+        let service = MessageService::new(store);
+
+        let body = "A".repeat(config::config().max_message_size - 4);
+        let mut options = AddOptions::default();
+        options
+            .attributes
+            .insert("ab".to_string(), "cd".to_string());
+        let msg = service
+            .add(DEFAULT_QUEUE, body.clone(), options.clone())
+            .await
+            .unwrap();
+        assert_eq!(msg.attributes["ab"], "cd");
+
+        options.attributes.insert("e".to_string(), String::new());
+        let result = service.add(DEFAULT_QUEUE, body, options).await;
+        assert_eq!(
+            result.unwrap_err(),
+            Error::PayloadTooLarge("Message body size is too large".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_message_size_uses_decoded_body() {
+        let store = Arc::new(MemoryStorage::new());
+        let service = MessageService::new(store);
+
+        let overrides = QueueConfigUpdate {
+            max_message_size: Some(4),
+            ..Default::default()
+        };
+        service.create_queue("small", overrides).await.unwrap();
+
+        let options = AddOptions {
+            encoding: BodyEncoding::Base64,
+            ..Default::default()
+        };
+        let msg = service
+            .add("small", BASE64.encode([0, 1, 2, 3]), options.clone())
+            .await
+            .unwrap();
+        assert_eq!(msg.encoding, BodyEncoding::Base64);
+
+        let result = service
+            .add("small", BASE64.encode([0, 1, 2, 3, 4]), options.clone())
+            .await;
+        assert_eq!(
+            result.unwrap_err(),
+            Error::PayloadTooLarge("Message body size is too large".to_string())
+        );
+
+        let result = service
+            .add("small", "not base64!".to_string(), options)
+            .await;
+        assert_eq!(
+            result.unwrap_err(),
+            Error::Validation("Body is not valid base64".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_add_batch_reports_each_message() {
+        let store = Arc::new(MemoryStorage::new());
+        let service = MessageService::new(store);
+
+        let dedup = AddOptions {
+            dedup_id: Some("order-42".to_string()),
+            ..Default::default()
+        };
+        let items = vec![
+            ("first".to_string(), dedup.clone()),
+            ("a".repeat(65537), AddOptions::default()),
+            ("second".to_string(), dedup),
+            ("third".to_string(), AddOptions::default()),
+        ];
+        let outcomes = service.add_batch(DEFAULT_QUEUE, items).await.unwrap();
+
+        assert_eq!(outcomes.len(), 4);
+        let first = outcomes[0].message.as_ref().unwrap();
+        assert_eq!(first.body, "first");
+        assert!(outcomes[1].message.is_none());
+        assert_eq!(
+            outcomes[1].error.as_deref(),
+            Some("Message body size is too large")
+        );
+        assert_eq!(outcomes[2].message.as_ref().unwrap().id, first.id);
+        assert_eq!(outcomes[3].message.as_ref().unwrap().body, "third");
+        assert!(outcomes[3].error.is_none());
+        assert_eq!(service.stats(DEFAULT_QUEUE).await.unwrap().ready, 2);
+    }
+
+    #[tokio::test]
+    async fn test_add_batch_validates_batch() {
+        let store = Arc::new(MemoryStorage::new());
+        let service = MessageService::new(store);
+
+        let result = service.add_batch(DEFAULT_QUEUE, Vec::new()).await;
+        assert_eq!(
+            result.unwrap_err(),
+            Error::Validation("No messages provided".to_string())
+        );
+
+        // A batch without valid messages does not create the queue
+        let items = vec![("a".repeat(65537), AddOptions::default())];
+        let outcomes = service.add_batch("orders", items).await.unwrap();
+        assert!(outcomes[0].error.is_some());
+        assert!(service.queue_config("orders").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_browse_returns_cursor_until_last_page() {
+        let store = Arc::new(MemoryStorage::new());
+        let service = MessageService::new(store);
+        for i in 0..3 {
+            service
+                .add(DEFAULT_QUEUE, format!("message {i}"), AddOptions::default())
+                .await
+                .unwrap();
+        }
+
+        let first = service.browse(DEFAULT_QUEUE, None, None, 2).await.unwrap();
+        assert_eq!(first.messages.len(), 2);
+        assert_eq!(first.next_cursor, Some(first.messages[1].id));
+
+        let last = service
+            .browse(DEFAULT_QUEUE, None, first.next_cursor, 2)
+            .await
+            .unwrap();
+        assert_eq!(last.messages.len(), 1);
+        assert_eq!(last.messages[0].body, "message 2");
+        assert!(last.next_cursor.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_browse_validates_limit() {
+        let store = Arc::new(MemoryStorage::new());
+        let service = MessageService::new(store);
+
+        for limit in [0, MAX_BROWSE_LIMIT + 1] {
+            let result = service.browse(DEFAULT_QUEUE, None, None, limit).await;
+            assert_eq!(
+                result.unwrap_err(),
+                Error::Validation(format!("limit must be between 1 and {MAX_BROWSE_LIMIT}"))
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_add_with_repeated_dedup_id_returns_original() {
+        let store = Arc::new(MemoryStorage::new());
+        let service = MessageService::new(store);
+
+        let options = AddOptions {
+            dedup_id: Some("order-42".to_string()),
+            ..Default::default()
+        };
+        let original = service
+            .add(DEFAULT_QUEUE, "first".to_string(), options.clone())
+            .await
+            .unwrap();
+        let duplicate = service
+            .add(DEFAULT_QUEUE, "second".to_string(), options)
+            .await
+            .unwrap();
+        assert_eq!(duplicate.id, original.id);
+        assert_eq!(duplicate.body, "first");
+        assert_eq!(service.stats(DEFAULT_QUEUE).await.unwrap().ready, 1);
+
+        let other = AddOptions {
+            dedup_id: Some("order-43".to_string()),
+            ..Default::default()
+        };
+        let added = service
+            .add(DEFAULT_QUEUE, "third".to_string(), other)
+            .await
+            .unwrap();
+        assert_ne!(added.id, original.id);
+    }
+
+    #[tokio::test]
+    async fn test_add_validates_dedup_id() {
+        let store = Arc::new(MemoryStorage::new());
+        let service = MessageService::new(store);
+
+        for dedup_id in [String::new(), "x".repeat(MAX_KEY_LENGTH + 1)] {
+            let options = AddOptions {
+                dedup_id: Some(dedup_id),
+                ..Default::default()
+            };
+            let result = service
+                .add(DEFAULT_QUEUE, "Hello".to_string(), options)
+                .await;
+            assert_eq!(
+                result.unwrap_err(),
+                Error::Validation(format!("dedup_id must be 1-{MAX_KEY_LENGTH} bytes long"))
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_add_validates_group_id() {
+        let store = Arc::new(MemoryStorage::new());
+        let service = MessageService::new(store);
+
+        let options = AddOptions {
+            group_id: Some(String::new()),
+            ..Default::default()
+        };
+        let result = service
+            .add(DEFAULT_QUEUE, "Hello".to_string(), options)
+            .await;
+        assert_eq!(
+            result.unwrap_err(),
+            Error::Validation(format!("group_id must be 1-{MAX_KEY_LENGTH} bytes long"))
+        );
+
+        for options in [
+            AddOptions {
+                priority: Some(5),
+                ..Default::default()
+            },
+            AddOptions {
+                delay_secs: Some(10),
+                ..Default::default()
+            },
+            AddOptions {
+                deliver_at: Some(0),
+                ..Default::default()
+            },
+        ] {
+            let options = AddOptions {
+                group_id: Some("customer-7".to_string()),
+                ..options
+            };
+            let result = service
+                .add(DEFAULT_QUEUE, "Hello".to_string(), options)
+                .await;
+            assert_eq!(
+                result.unwrap_err(),
+                Error::Validation(
+                    "group_id cannot be combined with priority, delay_secs or deliver_at"
+                        .to_string()
+                )
+            );
+        }
+
+        let options = AddOptions {
+            group_id: Some("customer-7".to_string()),
+            ..Default::default()
+        };
+        let msg = service
+            .add(DEFAULT_QUEUE, "Hello".to_string(), options)
+            .await
+            .unwrap();
+        assert_eq!(msg.group_id.as_deref(), Some("customer-7"));
+    }
+
+    #[tokio::test]
+    async fn test_add_rejects_empty_attribute_name() {
+        let store = Arc::new(MemoryStorage::new());
+        let service = MessageService::new(store);
+
+        let mut options = AddOptions::default();
+        options
+            .attributes
+            .insert(String::new(), "value".to_string());
+        let result = service
+            .add(DEFAULT_QUEUE, "Hello".to_string(), options)
+            .await;
+        assert_eq!(
+            result.unwrap_err(),
+            Error::Validation("Attribute names must not be empty".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_message_size_uses_queue_limit() {
+        let store = Arc::new(MemoryStorage::new());
+        let service = MessageService::new(store);
+
+        let overrides = QueueConfigUpdate {
+            max_message_size: Some(4),
+            ..Default::default()
+        };
+        service.create_queue("small", overrides).await.unwrap();
+
+        let options = AddOptions::default();
+        assert!(service
+            .add("small", "AAAA".to_string(), options.clone())
+            .await
+            .is_ok());
+        let result = service.add("small", "AAAAA".to_string(), options).await;
+        assert_eq!(
+            result.unwrap_err(),
+            Error::PayloadTooLarge("Message body size is too large".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_add_with_delay_is_scheduled() {
+        let store = Arc::new(MemoryStorage::new());
+        let service = MessageService::new(store);
+
+        let before = now_millis();
+        let options = AddOptions {
+            delay_secs: Some(60),
+            ..Default::default()
+        };
+        let msg = service
+            .add(DEFAULT_QUEUE, "later".to_string(), options)
+            .await
+            .unwrap();
+        assert_eq!(msg.state, MessageState::Scheduled);
+        assert!(msg.deliver_at.unwrap() >= before + 60_000);
+
+        let stats = service.stats(DEFAULT_QUEUE).await.unwrap();
+        assert_eq!(stats.scheduled, 1);
+        assert!(service
+            .get(DEFAULT_QUEUE, 1, None, None)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_add_with_past_deliver_at_is_ready() {
+        let store = Arc::new(MemoryStorage::new());
+        let service = MessageService::new(store);
+
+        let options = AddOptions {
+            deliver_at: Some(now_millis() - 1000),
+            ..Default::default()
+        };
+        let msg = service
+            .add(DEFAULT_QUEUE, "now".to_string(), options)
+            .await
+            .unwrap();
+        assert_eq!(msg.state, MessageState::Ready);
+        assert_eq!(
+            service
+                .get(DEFAULT_QUEUE, 1, None, None)
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn test_delivery_time() {
+        let both = AddOptions {
+            delay_secs: Some(1),
+            deliver_at: Some(1),
+            ..Default::default()
+        };
+        assert_eq!(
+            MessageService::delivery_time(&both, 0).unwrap_err(),
+            Error::Validation("Only one of delay_secs and deliver_at can be set".to_string())
+        );
+
+        let delay = AddOptions {
+            delay_secs: Some(5),
+            ..Default::default()
+        };
+        assert_eq!(MessageService::delivery_time(&delay, 1000), Ok(Some(6000)));
+
+        let too_large = AddOptions {
+            delay_secs: Some(u64::MAX),
+            ..Default::default()
+        };
+        assert!(MessageService::delivery_time(&too_large, 0).is_err());
+
+        assert_eq!(
+            MessageService::delivery_time(&AddOptions::default(), 0),
+            Ok(None)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_validates_lock_secs() {
+        let store = Arc::new(MemoryStorage::new());
+        let service = MessageService::new(store);
+        let max_lock_secs = config::config().max_lock_duration_secs;
+        let expected =
+            Error::Validation(format!("lock_secs must be between 1 and {max_lock_secs}"));
+
+        let result = service.get(DEFAULT_QUEUE, 1, Some(0), None).await;
+        assert_eq!(result.unwrap_err(), expected);
+        let result = service
+            .get(DEFAULT_QUEUE, 1, Some(max_lock_secs + 1), None)
+            .await;
+        assert_eq!(result.unwrap_err(), expected);
+        assert!(service
+            .get(DEFAULT_QUEUE, 1, Some(max_lock_secs), None)
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_extend_validates_extend_secs() {
+        let store = Arc::new(MemoryStorage::new());
+        let service = MessageService::new(store);
+        let max_lock_secs = config::config().max_lock_duration_secs;
+        let expected =
+            Error::Validation(format!("extend_secs must be between 1 and {max_lock_secs}"));
+        let receipt = Message::new("held".to_string()).new_receipt();
+
+        for extend_secs in [0, max_lock_secs + 1, 18446744073709551] {
+            let result = service
+                .extend(DEFAULT_QUEUE, vec![receipt.clone()], extend_secs)
+                .await;
+            assert_eq!(result.unwrap_err(), expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_waits_for_added_message() {
+        let store = Arc::new(MemoryStorage::new());
+        let service = MessageService::new(store);
+
+        let producer = service.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            producer
+                .add(DEFAULT_QUEUE, "late".to_string(), AddOptions::default())
+                .await
+                .unwrap();
+        });
+
+        let started = Instant::now();
+        let messages = service.get(DEFAULT_QUEUE, 1, None, Some(5)).await.unwrap();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].body, "late");
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[tokio::test]
+    async fn test_get_waits_for_group_to_be_released() {
+        let store = Arc::new(MemoryStorage::new());
+        let service = MessageService::new(store);
+        for body in ["first", "second"] {
+            let options = AddOptions {
+                group_id: Some("customer-7".to_string()),
+                ..Default::default()
+            };
+            service
+                .add(DEFAULT_QUEUE, body.to_string(), options)
+                .await
+                .unwrap();
+        }
+        let first = service.get(DEFAULT_QUEUE, 1, None, None).await.unwrap();
+
+        let consumer = service.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            consumer
+                .delete(DEFAULT_QUEUE, vec![first[0].receipt.clone().unwrap()])
+                .await
+                .unwrap();
+        });
+
+        let started = Instant::now();
+        let messages = service.get(DEFAULT_QUEUE, 1, None, Some(5)).await.unwrap();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].body, "second");
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_releases_waiting_get() {
+        let store = Arc::new(MemoryStorage::new());
+        let service = MessageService::new(store);
+
+        let stopper = service.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            stopper.shutdown();
+        });
+
+        let started = Instant::now();
+        let messages = service.get(DEFAULT_QUEUE, 1, None, Some(5)).await.unwrap();
+        assert!(messages.is_empty());
+        assert!(started.elapsed() < Duration::from_secs(5));
+
+        // New deliveries and messages are refused while the server drains
+        let result = service.get(DEFAULT_QUEUE, 1, None, Some(5)).await;
+        assert!(matches!(result, Err(Error::Unavailable(_))));
+        let result = service
+            .add(DEFAULT_QUEUE, "late".to_string(), AddOptions::default())
+            .await;
+        assert!(matches!(result, Err(Error::Unavailable(_))));
+        let result = service
+            .add_batch(
+                DEFAULT_QUEUE,
+                vec![("late".to_string(), AddOptions::default())],
+            )
+            .await;
+        assert!(matches!(result, Err(Error::Unavailable(_))));
+    }
+
+    #[tokio::test]
+    async fn test_get_wait_times_out() {
+        let store = Arc::new(MemoryStorage::new());
+        let service = MessageService::new(store);
+
+        let started = Instant::now();
+        let messages = service.get(DEFAULT_QUEUE, 1, None, Some(1)).await.unwrap();
+        assert!(messages.is_empty());
+        assert!(started.elapsed() >= Duration::from_secs(1));
+    }
+
+    #[tokio::test]
+    async fn test_get_rejects_long_wait() {
+        let store = Arc::new(MemoryStorage::new());
+        let service = MessageService::new(store);
+
+        let result = service
+            .get(DEFAULT_QUEUE, 1, None, Some(MAX_WAIT_SECS + 1))
+            .await;
+        assert_eq!(
+            result.unwrap_err(),
+            Error::Validation(format!("wait_secs must be at most {MAX_WAIT_SECS}"))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_expiry_time() {
+        let mut queue_config = config::config().queue_defaults();
+        queue_config.ttl_secs = 0;
+
+        let none = AddOptions::default();
+        assert_eq!(
+            MessageService::expiry_time(&none, &queue_config, 1000),
+            Ok(None)
+        );
+
+        let ttl = AddOptions {
+            ttl_secs: Some(5),
+            ..Default::default()
+        };
+        assert_eq!(
+            MessageService::expiry_time(&ttl, &queue_config, 1000),
+            Ok(Some(6000))
+        );
+
+        queue_config.ttl_secs = 10;
+        assert_eq!(
+            MessageService::expiry_time(&none, &queue_config, 1000),
+            Ok(Some(11000))
+        );
+
+        let zero = AddOptions {
+            ttl_secs: Some(0),
+            ..Default::default()
+        };
+        assert_eq!(
+            MessageService::expiry_time(&zero, &queue_config, 1000).unwrap_err(),
+            Error::Validation("ttl_secs must be greater than 0".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_create_queue_applies_defaults() {
+        let store = Arc::new(MemoryStorage::new());
+        let service = MessageService::new(store);
+
+        let overrides = QueueConfigUpdate {
+            lock_duration_secs: Some(5),
+            ..Default::default()
+        };
+        let created = service.create_queue("fast", overrides).await.unwrap();
+        let defaults = config::config().queue_defaults();
+        assert_eq!(created.lock_duration_secs, 5);
+        assert_eq!(created.max_retries, defaults.max_retries);
+        assert_eq!(created.max_message_size, defaults.max_message_size);
+    }
+
+    #[tokio::test]
+    async fn test_validate_queue_config() {
+        let valid = QueueConfigUpdate {
+            lock_duration_secs: Some(1),
+            max_retries: Some(0),
+            max_message_size: Some(1),
+            ttl_secs: Some(0),
+            dead_letter_expired: Some(true),
+            dedup_window_secs: Some(0),
+        };
+        assert!(MessageService::validate_queue_config(&valid).is_ok());
+
+        let max_lock_secs = config::config().max_lock_duration_secs;
+        for secs in [0, max_lock_secs + 1, 18446744073709551] {
+            let bad_lock = QueueConfigUpdate {
+                lock_duration_secs: Some(secs),
+                ..Default::default()
+            };
+            assert_eq!(
+                MessageService::validate_queue_config(&bad_lock).unwrap_err(),
+                Error::Validation(format!(
+                    "lock_duration_secs must be between 1 and {max_lock_secs}"
+                ))
+            );
+        }
+
+        let long_dedup = QueueConfigUpdate {
+            dedup_window_secs: Some(MAX_DEDUP_WINDOW_SECS + 1),
+            ..Default::default()
+        };
+        assert_eq!(
+            MessageService::validate_queue_config(&long_dedup).unwrap_err(),
+            Error::Validation(format!(
+                "dedup_window_secs must be at most {MAX_DEDUP_WINDOW_SECS}"
+            ))
+        );
+
+        let zero_size = QueueConfigUpdate {
+            max_message_size: Some(0),
+            ..Default::default()
+        };
+        assert_eq!(
+            MessageService::validate_queue_config(&zero_size).unwrap_err(),
+            Error::Validation("max_message_size must be greater than 0".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_validate_queue_name() {
+        assert!(MessageService::validate_queue_name("orders").is_ok());
+        assert!(MessageService::validate_queue_name("orders-eu_1").is_ok());
+        assert!(MessageService::validate_queue_name(&"a".repeat(MAX_QUEUE_NAME_LENGTH)).is_ok());
+
+        assert!(MessageService::validate_queue_name("").is_err());
+        assert!(MessageService::validate_queue_name("orders/eu").is_err());
+        assert!(MessageService::validate_queue_name("orders eu").is_err());
+        assert!(
+            MessageService::validate_queue_name(&"a".repeat(MAX_QUEUE_NAME_LENGTH + 1)).is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_default_queue_cannot_be_deleted() {
+        let store = Arc::new(MemoryStorage::new());
+        let service = MessageService::new(store);
+
+        let result = service.delete_queue(DEFAULT_QUEUE).await;
+        assert_eq!(
+            result.unwrap_err(),
+            Error::Validation("The default queue cannot be deleted".to_string())
+        );
     }
 
     #[tokio::test]
@@ -106,11 +1286,36 @@ mod tests {
         let ids = vec!["invalid".to_string()];
         let result = MessageService::validate_ids(&ids);
         assert!(result.is_err());
-        assert_eq!(result.unwrap_err(), "Invalid message IDs: [\"invalid\"]");
+        assert_eq!(
+            result.unwrap_err(),
+            Error::Validation("Invalid message IDs: [\"invalid\"]".to_string())
+        );
 
         let ids = vec![];
         let result = MessageService::validate_ids(&ids);
         assert!(result.is_err());
-        assert_eq!(result.unwrap_err(), "No message IDs provided");
+        assert_eq!(
+            result.unwrap_err(),
+            Error::Validation("No message IDs provided".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_validate_receipts() {
+        let receipt = Message::new("Hello".to_string()).new_receipt();
+        assert!(MessageService::validate_receipts(&[receipt]).is_ok());
+
+        let id = Uuid::now_v7().to_string();
+        let result = MessageService::validate_receipts(std::slice::from_ref(&id));
+        assert_eq!(
+            result.unwrap_err(),
+            Error::Validation(format!("Invalid receipts: [{id:?}]"))
+        );
+
+        let result = MessageService::validate_receipts(&[]);
+        assert_eq!(
+            result.unwrap_err(),
+            Error::Validation("No receipts provided".to_string())
+        );
     }
 }
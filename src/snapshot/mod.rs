@@ -0,0 +1,283 @@
+use crate::errors::Error;
+use crate::types::{DeadLetter, Message, QueueConfig, Snapshot};
+use serde::{Deserialize, Serialize};
+
+/// Version of the snapshot format written by [`encode`]
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+/// First line of a snapshot
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotHeader {
+    pub version: u32,
+    /// Name of the exported queue
+    pub queue: String,
+    /// Unix timestamp in milliseconds at which the snapshot was taken
+    pub exported_at: i64,
+    pub config: QueueConfig,
+    /// Number of message lines that follow
+    pub messages: usize,
+    /// Number of dead letter lines that follow
+    pub dead_letters: usize,
+}
+
+/// Every line after the header holds one message or dead letter
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SnapshotEntry {
+    Message(Message),
+    DeadLetter(DeadLetter),
+}
+
+/// Writes a snapshot of `queue` as JSON Lines: a header, then the messages,
+/// then the dead letters.
+pub fn encode(queue: &str, snapshot: Snapshot, exported_at: i64) -> Result<String, Error> {
+    let header = SnapshotHeader {
+        version: SNAPSHOT_VERSION,
+        queue: queue.to_string(),
+        exported_at,
+        config: snapshot.config,
+        messages: snapshot.messages.len(),
+        dead_letters: snapshot.dead_letters.len(),
+    };
+    let entries = snapshot
+        .messages
+        .into_iter()
+        .map(SnapshotEntry::Message)
+        .chain(
+            snapshot
+                .dead_letters
+                .into_iter()
+                .map(SnapshotEntry::DeadLetter),
+        );
+
+    let mut output = line(&header)?;
+    for entry in entries {
+        output.push_str(&line(&entry)?);
+    }
+    Ok(output)
+}
+
+fn line(value: &impl Serialize) -> Result<String, Error> {
+    let mut line = serde_json::to_string(value)
+        .map_err(|e| Error::Storage(format!("Failed to encode snapshot: {e}")))?;
+    line.push('\n');
+    Ok(line)
+}
+
+/// Writes snapshots of several queues into one JSON Lines document, each a
+/// header followed by its entries as written by [`encode`].
+pub fn encode_all(queues: Vec<(String, Snapshot)>, exported_at: i64) -> Result<String, Error> {
+    let mut output = String::new();
+    for (queue, snapshot) in queues {
+        output.push_str(&encode(&queue, snapshot, exported_at)?);
+    }
+    Ok(output)
+}
+
+/// Reads a snapshot written by [`encode`]. Fails if the snapshot has an
+/// unsupported version, an unreadable line, or fewer lines than its header announces.
+pub fn decode(input: &str) -> Result<Snapshot, Error> {
+    let mut lines = lines(input);
+    let header = lines
+        .next()
+        .ok_or_else(|| Error::Validation("Snapshot is empty".to_string()))?;
+    let header = parse_header(header)?;
+
+    read_entries(header, lines)
+}
+
+/// Reads the snapshots of all queues written by [`encode_all`], keyed by queue name.
+/// An empty document holds no queues.
+pub fn decode_all(input: &str) -> Result<Vec<(String, Snapshot)>, Error> {
+    let mut lines = lines(input);
+    let mut queues = Vec::new();
+    while let Some(header) = lines.next() {
+        let header = parse_header(header)?;
+        let queue = header.queue.clone();
+        let entries = header
+            .messages
+            .checked_add(header.dead_letters)
+            .ok_or_else(|| Error::Validation("Snapshot header counts overflow".to_string()))?;
+        queues.push((queue, read_entries(header, lines.by_ref().take(entries))?));
+    }
+    Ok(queues)
+}
+
+/// Non-blank lines of `input` with their zero-based line index
+fn lines(input: &str) -> impl Iterator<Item = (usize, &str)> {
+    input
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+}
+
+fn parse_header((_, line): (usize, &str)) -> Result<SnapshotHeader, Error> {
+    let header: SnapshotHeader = serde_json::from_str(line)
+        .map_err(|e| Error::Validation(format!("Invalid snapshot header: {e}")))?;
+    if header.version != SNAPSHOT_VERSION {
+        return Err(Error::Validation(format!(
+            "Unsupported snapshot version {}",
+            header.version
+        )));
+    }
+    Ok(header)
+}
+
+fn read_entries<'a>(
+    header: SnapshotHeader,
+    lines: impl Iterator<Item = (usize, &'a str)>,
+) -> Result<Snapshot, Error> {
+    // The counts come from the input, so they only bound the entries and do not size anything
+    let mut snapshot = Snapshot {
+        config: header.config,
+        messages: Vec::new(),
+        dead_letters: Vec::new(),
+    };
+    for (index, line) in lines {
+        match serde_json::from_str(line) {
+            Ok(SnapshotEntry::Message(message)) => snapshot.messages.push(message),
+            Ok(SnapshotEntry::DeadLetter(dead_letter)) => snapshot.dead_letters.push(dead_letter),
+            Err(e) => {
+                return Err(Error::Validation(format!(
+                    "Invalid snapshot entry on line {}: {e}",
+                    index + 1
+                )))
+            }
+        }
+    }
+
+    if snapshot.messages.len() != header.messages
+        || snapshot.dead_letters.len() != header.dead_letters
+    {
+        return Err(Error::Validation(format!(
+            "Snapshot is incomplete: expected {} messages and {} dead letters, found {} and {}",
+            header.messages,
+            header.dead_letters,
+            snapshot.messages.len(),
+            snapshot.dead_letters.len()
+        )));
+    }
+
+    Ok(snapshot)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{DeadReason, MessageState};
+
+    fn snapshot() -> Snapshot {
+        let mut locked = Message::new("locked".to_string());
+        locked.state = MessageState::Processing;
+        locked.lock_until = Some(1_000);
+        locked.retry_count = 2;
+        let mut dead = Message::new("dead".to_string());
+        dead.state = MessageState::Dead;
+
+        Snapshot {
+            config: crate::config::config().queue_defaults(),
+            messages: vec![Message::new("ready".to_string()), locked],
+            dead_letters: vec![DeadLetter {
+                message: dead,
+                reason: DeadReason::MaxRetriesExceeded,
+                failed_at: 2_000,
+            }],
+        }
+    }
+
+    #[test]
+    fn test_encode_decode_round_trip() {
+        let original = snapshot();
+        let encoded = encode("orders", original.clone(), 3_000).unwrap();
+        assert_eq!(encoded.lines().count(), 4);
+
+        let header: SnapshotHeader = serde_json::from_str(encoded.lines().next().unwrap()).unwrap();
+        assert_eq!(header.version, SNAPSHOT_VERSION);
+        assert_eq!(header.queue, "orders");
+        assert_eq!(header.messages, 2);
+        assert_eq!(header.dead_letters, 1);
+
+        let decoded = decode(&encoded).unwrap();
+        assert_eq!(decoded.config, original.config);
+        assert_eq!(decoded.messages[1].id, original.messages[1].id);
+        assert_eq!(decoded.messages[1].state, MessageState::Processing);
+        assert_eq!(decoded.messages[1].lock_until, Some(1_000));
+        assert_eq!(decoded.messages[1].retry_count, 2);
+        assert_eq!(decoded.dead_letters[0].message.body, "dead");
+        assert_eq!(decoded.dead_letters[0].failed_at, 2_000);
+    }
+
+    #[test]
+    fn test_encode_decode_all_queues() {
+        let queues = vec![
+            ("default".to_string(), snapshot()),
+            (
+                "empty".to_string(),
+                Snapshot {
+                    config: crate::config::config().queue_defaults(),
+                    messages: Vec::new(),
+                    dead_letters: Vec::new(),
+                },
+            ),
+            ("orders".to_string(), snapshot()),
+        ];
+        let encoded = encode_all(queues, 3_000).unwrap();
+        assert_eq!(encoded.lines().count(), 9);
+
+        let decoded = decode_all(&encoded).unwrap();
+        let names: Vec<&str> = decoded.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, vec!["default", "empty", "orders"]);
+        assert!(decoded[1].1.messages.is_empty());
+        assert_eq!(decoded[2].1.messages.len(), 2);
+        assert_eq!(decoded[2].1.dead_letters.len(), 1);
+        assert!(decode_all("").unwrap().is_empty());
+
+        let truncated: String = encoded.lines().take(7).map(|l| format!("{l}\n")).collect();
+        let err = decode_all(&truncated).unwrap_err();
+        assert!(
+            err.message().starts_with("Snapshot is incomplete"),
+            "{err:?}"
+        );
+    }
+
+    #[test]
+    fn test_decode_rejects_invalid_snapshots() {
+        let encoded = encode("orders", snapshot(), 3_000).unwrap();
+        let truncated: String = encoded.lines().take(2).map(|l| format!("{l}\n")).collect();
+        let future = encoded.replacen("\"version\":1", "\"version\":2", 1);
+        let corrupt = format!("{encoded}{{\"kind\":\"message\"}}\n");
+
+        for (input, error) in [
+            ("", "Snapshot is empty"),
+            ("not json\n", "Invalid snapshot header"),
+            (future.as_str(), "Unsupported snapshot version 2"),
+            (corrupt.as_str(), "Invalid snapshot entry on line 5"),
+            (truncated.as_str(), "Snapshot is incomplete"),
+        ] {
+            let err = decode(input).unwrap_err();
+            assert!(
+                matches!(&err, Error::Validation(message) if message.starts_with(error)),
+                "{input:?} failed with {err:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_decode_rejects_huge_header_counts() {
+        let encoded = encode("orders", snapshot(), 3_000).unwrap();
+        let header = encoded.lines().next().unwrap();
+        let huge = header.replacen("\"messages\":2", &format!("\"messages\":{}", usize::MAX), 1);
+        assert_ne!(huge, header);
+
+        let err = decode(&format!("{huge}\n")).unwrap_err();
+        assert!(
+            matches!(&err, Error::Validation(message) if message.starts_with("Snapshot is incomplete")),
+            "{err:?}"
+        );
+        let err = decode_all(&format!("{huge}\n")).unwrap_err();
+        assert!(
+            matches!(&err, Error::Validation(message) if message == "Snapshot header counts overflow"),
+            "{err:?}"
+        );
+    }
+}
@@ -0,0 +1,68 @@
+use crate::storage::memory::base::Checkpoint;
+use crate::types::{DeadLetter, Message, ProcessingRestore, QueueConfig};
+use serde::{Deserialize, Serialize};
+
+/// A single record in the storage log.
+/// Each entry describes one state transition and is written as a line of JSON.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub(crate) enum LogEntry {
+    /// An empty queue was created
+    CreateQueue { queue: String, config: QueueConfig },
+    /// The configuration of a queue was changed
+    UpdateQueue { queue: String, config: QueueConfig },
+    /// A queue and all of its messages were removed
+    DeleteQueue { queue: String },
+    /// A new message was enqueued
+    Add { queue: String, message: Message },
+    /// Ready messages were handed to a consumer and locked until `lock_until`
+    Get {
+        queue: String,
+        ids: Vec<String>,
+        lock_until: i64,
+        /// Receipt handles issued for `ids`, in the same order
+        #[serde(default)]
+        receipts: Vec<String>,
+    },
+    /// Scheduled messages due at or before `until` became ready
+    Promote { queue: String, until: i64 },
+    /// Waiting messages whose time-to-live ran out at or before `until` expired
+    Expire { queue: String, until: i64 },
+    /// Processing messages were acknowledged
+    Delete { queue: String, ids: Vec<String> },
+    /// Locks of processing messages were pushed forward by `extend_secs` at `now`
+    Extend {
+        queue: String,
+        ids: Vec<String>,
+        extend_secs: u64,
+        now: i64,
+    },
+    /// Processing messages were returned to the queue
+    Retry { queue: String, ids: Vec<String> },
+    /// All messages of a queue were removed
+    Purge { queue: String },
+    /// The reaper requeued or dead-lettered messages with expired locks
+    Reap {
+        queue: String,
+        retried: Vec<String>,
+        dead: Vec<String>,
+        failed_at: i64,
+    },
+    /// Dead letters were moved back to the queue
+    Redrive { queue: String, ids: Vec<String> },
+    /// All dead letters of a queue were removed
+    PurgeDead { queue: String },
+    /// Messages and dead letters of a snapshot were restored
+    Import {
+        queue: String,
+        messages: Vec<Message>,
+        dead_letters: Vec<DeadLetter>,
+        processing: ProcessingRestore,
+    },
+    /// The complete state of a queue, replacing whatever the entries before
+    /// held for it. Compaction rewrites the log as one checkpoint per queue
+    Checkpoint {
+        queue: String,
+        state: Box<Checkpoint>,
+    },
+}
@@ -0,0 +1,1275 @@
+use crate::config;
+use crate::errors::Error;
+use crate::storage::memory::base::BaseMemoryStorage;
+use crate::storage::notify::QueueNotifier;
+use crate::storage::queue_not_found;
+use crate::storage::traits::Storage;
+use crate::time::now_millis;
+use crate::types::{
+    DeadLetter, ImportSummary, Message, MessageState, ProcessingRestore, QueueConfig,
+    QueueConfigUpdate, QueueInfo, QueueStats, ReapResult, ReceiptOutcome, ReceiptStatus,
+    RedriveOutcome, Snapshot, DEFAULT_QUEUE,
+};
+use async_trait::async_trait;
+use entry::LogEntry;
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+use std::sync::Arc;
+use tokio::sync::{Mutex, MutexGuard, Notify};
+use tracing::{info, warn};
+use uuid::Uuid;
+use writer::{LogWriter, Written};
+
+mod entry;
+mod writer;
+
+/// Size the log must reach before it is compacted for the first time. After
+/// that it is compacted whenever it doubles in size since the last compaction.
+const COMPACT_MIN_LEN: u64 = 16 * 1024 * 1024;
+
+/// Durable storage backed by an append-only log.
+///
+/// Every state transition is appended to the log before the call returns,
+/// and the in-memory queues are rebuilt by replaying the log on startup.
+/// Once the log has grown well past the state it describes, it is compacted
+/// into one checkpoint entry per queue.
+pub struct LogStorage {
+    inner: Mutex<LogState>,
+    notifier: QueueNotifier,
+}
+
+struct LogState {
+    queues: HashMap<String, BaseMemoryStorage>,
+    defaults: QueueConfig,
+    writer: LogWriter,
+    /// Completion of the latest write queued under the lock; waiting for it
+    /// covers every write queued before it too
+    pending: Option<Written>,
+    /// Size of the log right after it was last compacted
+    compacted_len: u64,
+}
+
+impl LogStorage {
+    /// Opens the log at `path`, creating it if missing, and replays it.
+    ///
+    /// A torn entry at the end of the log (e.g. from a crash mid-write) is
+    /// discarded. Any other unreadable entry is reported as an error.
+    pub async fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        let file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(path)?;
+
+        let defaults = config::config().queue_defaults();
+        let mut queues = HashMap::new();
+        queues.insert(DEFAULT_QUEUE.to_string(), BaseMemoryStorage::new(defaults));
+        let (entries, valid_len) = Self::replay(&mut queues, defaults, &file).await?;
+        // Replayed operations all happened "now"; rates only cover live traffic
+        for queue in queues.values_mut() {
+            queue.reset_rates();
+        }
+
+        if valid_len < file.metadata()?.len() {
+            warn!(
+                "Discarding torn entry at the end of storage log {}",
+                path.display()
+            );
+            file.set_len(valid_len)?;
+        }
+
+        info!(
+            "Replayed {} entries from storage log {}",
+            entries,
+            path.display()
+        );
+
+        let storage = LogStorage {
+            inner: Mutex::new(LogState {
+                queues,
+                defaults,
+                writer: LogWriter::spawn(path, file, config::config().storage_sync)?,
+                pending: None,
+                compacted_len: 0,
+            }),
+            notifier: QueueNotifier::new(),
+        };
+        if storage.inner.lock().await.needs_compaction() {
+            storage.compact().await.map_err(io::Error::other)?;
+        }
+        Ok(storage)
+    }
+
+    /// Rewrites the log as one checkpoint entry per queue, dropping the history
+    /// that led to the current state.
+    pub async fn compact(&self) -> Result<(), Error> {
+        let mut state = self.inner.lock().await;
+        state.compact()?;
+        Self::commit(state).await
+    }
+
+    /// Releases the lock and waits until the entries appended under it are written.
+    async fn commit(mut state: MutexGuard<'_, LogState>) -> Result<(), Error> {
+        let pending = state.pending.take();
+        drop(state);
+        match pending {
+            Some(written) => written.wait().await,
+            None => Ok(()),
+        }
+    }
+
+    async fn replay(
+        queues: &mut HashMap<String, BaseMemoryStorage>,
+        defaults: QueueConfig,
+        file: &File,
+    ) -> io::Result<(usize, u64)> {
+        let mut reader = BufReader::new(file);
+        let mut line = String::new();
+        let mut entries = 0;
+        let mut valid_len = 0;
+
+        loop {
+            line.clear();
+            let read = reader.read_line(&mut line)?;
+            if read == 0 {
+                break;
+            }
+
+            // Only the last line can lack a terminator, left by an interrupted write
+            if !line.ends_with('\n') {
+                break;
+            }
+
+            let entry = serde_json::from_str::<LogEntry>(&line).map_err(|e| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Corrupted storage log entry {}: {e}", entries + 1),
+                )
+            })?;
+            Self::apply(queues, defaults, entry).await;
+            entries += 1;
+            valid_len += read as u64;
+        }
+
+        Ok((entries, valid_len))
+    }
+
+    async fn apply(
+        queues: &mut HashMap<String, BaseMemoryStorage>,
+        defaults: QueueConfig,
+        entry: LogEntry,
+    ) {
+        fn queue(
+            queues: &mut HashMap<String, BaseMemoryStorage>,
+            defaults: QueueConfig,
+            name: String,
+        ) -> &mut BaseMemoryStorage {
+            queues
+                .entry(name)
+                .or_insert_with(|| BaseMemoryStorage::new(defaults))
+        }
+
+        // Replayed entries were validated when they were first written, so the
+        // in-memory operations cannot fail here.
+        let _ = match entry {
+            LogEntry::CreateQueue {
+                queue: name,
+                config,
+            } => {
+                queue(queues, defaults, name).set_config(config);
+                Ok(())
+            }
+            LogEntry::UpdateQueue {
+                queue: name,
+                config,
+            } => {
+                queue(queues, defaults, name).set_config(config);
+                Ok(())
+            }
+            LogEntry::DeleteQueue { queue: name } => {
+                queues.remove(&name);
+                Ok(())
+            }
+            LogEntry::Add {
+                queue: name,
+                message,
+            } => queue(queues, defaults, name).add(message).await,
+            LogEntry::Get {
+                queue: name,
+                ids,
+                lock_until,
+                receipts,
+            } => {
+                queue(queues, defaults, name).claim(&ids, lock_until, &receipts);
+                Ok(())
+            }
+            LogEntry::Promote { queue: name, until } => {
+                queue(queues, defaults, name).promote_due(until);
+                Ok(())
+            }
+            LogEntry::Expire { queue: name, until } => {
+                queue(queues, defaults, name).expire_due(until);
+                Ok(())
+            }
+            LogEntry::Delete { queue: name, ids } => {
+                queue(queues, defaults, name).delete(ids).await
+            }
+            LogEntry::Extend {
+                queue: name,
+                ids,
+                extend_secs,
+                now,
+            } => queue(queues, defaults, name)
+                .extend(&ids, extend_secs, now)
+                .await
+                .map(|_| ()),
+            LogEntry::Retry { queue: name, ids } => queue(queues, defaults, name).retry(ids).await,
+            LogEntry::Purge { queue: name } => queue(queues, defaults, name).purge().await,
+            LogEntry::Reap {
+                queue: name,
+                retried,
+                dead,
+                failed_at,
+            } => queue(queues, defaults, name)
+                .process_expired(retried, dead, failed_at)
+                .await
+                .map(|_| ()),
+            LogEntry::Redrive { queue: name, ids } => {
+                queue(queues, defaults, name).redrive(ids).await
+            }
+            LogEntry::PurgeDead { queue: name } => queue(queues, defaults, name).purge_dead().await,
+            LogEntry::Import {
+                queue: name,
+                messages,
+                dead_letters,
+                processing,
+            } => {
+                queue(queues, defaults, name).import(messages, dead_letters, processing);
+                Ok(())
+            }
+            LogEntry::Checkpoint { queue: name, state } => {
+                queues.insert(name, BaseMemoryStorage::restore(*state));
+                Ok(())
+            }
+        };
+    }
+}
+
+impl LogState {
+    fn queue(&self, name: &str) -> Result<&BaseMemoryStorage, Error> {
+        self.queues.get(name).ok_or_else(|| queue_not_found(name))
+    }
+
+    fn queue_mut(&mut self, name: &str) -> Result<&mut BaseMemoryStorage, Error> {
+        self.queues
+            .get_mut(name)
+            .ok_or_else(|| queue_not_found(name))
+    }
+
+    fn create_queue(&mut self, queue: &str, config: QueueConfig) -> Result<(), Error> {
+        self.append(&LogEntry::CreateQueue {
+            queue: queue.to_string(),
+            config,
+        })?;
+        self.queues
+            .insert(queue.to_string(), BaseMemoryStorage::new(config));
+        Ok(())
+    }
+
+    /// Promotes scheduled messages of `queue` that are due at `now`, logging
+    /// the promotion so replay moves the same messages.
+    fn promote_due(&mut self, queue: &str, now: i64) -> Result<usize, Error> {
+        match self.queue(queue)?.next_delivery() {
+            Some(deliver_at) if deliver_at <= now => {}
+            _ => return Ok(0),
+        }
+
+        self.append(&LogEntry::Promote {
+            queue: queue.to_string(),
+            until: now,
+        })?;
+        Ok(self.queue_mut(queue)?.promote_due(now))
+    }
+
+    /// Expires waiting messages of `queue` whose time-to-live ran out at `now`,
+    /// logging the expiry so replay removes the same messages.
+    fn expire_due(&mut self, queue: &str, now: i64) -> Result<usize, Error> {
+        match self.queue(queue)?.next_expiry() {
+            Some(expires_at) if expires_at <= now => {}
+            _ => return Ok(0),
+        }
+
+        self.append(&LogEntry::Expire {
+            queue: queue.to_string(),
+            until: now,
+        })?;
+        Ok(self.queue_mut(queue)?.expire_due(now))
+    }
+
+    /// Queues `entry` for writing. Callers apply the entry in memory only once
+    /// this succeeded, and wait for the write with [`LogStorage::commit`].
+    fn append(&mut self, entry: &LogEntry) -> Result<(), Error> {
+        let mut line = Vec::new();
+        encode(entry, &mut line)?;
+        self.pending = Some(self.writer.append(line)?);
+        Ok(())
+    }
+
+    fn needs_compaction(&self) -> bool {
+        self.writer.len() >= COMPACT_MIN_LEN.max(self.compacted_len.saturating_mul(2))
+    }
+
+    /// Queues replacing the log with a checkpoint of every queue.
+    fn compact(&mut self) -> Result<(), Error> {
+        let mut names: Vec<&String> = self.queues.keys().collect();
+        names.sort();
+
+        let mut contents = Vec::new();
+        for name in names {
+            let entry = LogEntry::Checkpoint {
+                queue: name.clone(),
+                state: Box::new(self.queues[name].checkpoint()),
+            };
+            encode(&entry, &mut contents)?;
+        }
+
+        self.compacted_len = contents.len() as u64;
+        self.pending = Some(self.writer.rewrite(contents)?);
+        Ok(())
+    }
+}
+
+/// Appends `entry` to `buf` as a line of JSON.
+fn encode(entry: &LogEntry, buf: &mut Vec<u8>) -> Result<(), Error> {
+    serde_json::to_writer(&mut *buf, entry)
+        .map_err(|e| Error::Storage(format!("Failed to encode storage log entry: {e}")))?;
+    buf.push(b'\n');
+    Ok(())
+}
+
+#[async_trait]
+impl Storage for LogStorage {
+    async fn create_queue(&self, queue: &str, config: QueueConfig) -> Result<(), Error> {
+        let mut state = self.inner.lock().await;
+        if state.queues.contains_key(queue) {
+            return Err(Error::Conflict(format!("Queue '{queue}' already exists")));
+        }
+
+        state.create_queue(queue, config)?;
+        Self::commit(state).await
+    }
+
+    async fn delete_queue(&self, queue: &str) -> Result<(), Error> {
+        let mut state = self.inner.lock().await;
+        state.queue(queue)?;
+        state.append(&LogEntry::DeleteQueue {
+            queue: queue.to_string(),
+        })?;
+        state.queues.remove(queue);
+        self.notifier.remove(queue);
+        Self::commit(state).await
+    }
+
+    async fn list_queues(&self) -> Result<Vec<QueueInfo>, Error> {
+        let state = self.inner.lock().await;
+        let mut infos = Vec::with_capacity(state.queues.len());
+        for (name, storage) in &state.queues {
+            infos.push(QueueInfo {
+                name: name.clone(),
+                stats: storage.stats().await?,
+                config: storage.config(),
+            });
+        }
+        infos.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Ok(infos)
+    }
+
+    async fn queue_config(&self, queue: &str) -> Result<Option<QueueConfig>, Error> {
+        let state = self.inner.lock().await;
+        Ok(state.queues.get(queue).map(|storage| storage.config()))
+    }
+
+    async fn update_queue_config(
+        &self,
+        queue: &str,
+        update: QueueConfigUpdate,
+    ) -> Result<QueueConfig, Error> {
+        let mut state = self.inner.lock().await;
+        let config = state.queue(queue)?.config().merge(&update);
+        state.append(&LogEntry::UpdateQueue {
+            queue: queue.to_string(),
+            config,
+        })?;
+        state.queue_mut(queue)?.set_config(config);
+        Self::commit(state).await?;
+        Ok(config)
+    }
+
+    async fn add(&self, queue: &str, msg: Message) -> Result<Message, Error> {
+        let mut added = self.add_batch(queue, vec![msg]).await?;
+        Ok(added.remove(0))
+    }
+
+    async fn add_batch(&self, queue: &str, msgs: Vec<Message>) -> Result<Vec<Message>, Error> {
+        let mut state = self.inner.lock().await;
+        if !state.queues.contains_key(queue) {
+            let defaults = state.defaults;
+            state.create_queue(queue, defaults)?;
+        }
+
+        let now = now_millis();
+        let mut added = Vec::with_capacity(msgs.len());
+        let mut enqueued = false;
+        for msg in msgs {
+            if let Some(original) = msg
+                .dedup_id
+                .as_deref()
+                .and_then(|dedup_id| state.queue(queue).ok()?.find_duplicate(dedup_id, now))
+            {
+                added.push(original.clone());
+                continue;
+            }
+
+            state.append(&LogEntry::Add {
+                queue: queue.to_string(),
+                message: msg.clone(),
+            })?;
+            state.queue_mut(queue)?.add(msg.clone()).await?;
+            added.push(msg);
+            enqueued = true;
+        }
+        if enqueued {
+            self.notifier.notify(queue);
+        }
+        Self::commit(state).await?;
+        Ok(added)
+    }
+
+    async fn get(
+        &self,
+        queue: &str,
+        count: usize,
+        lock_secs: Option<u64>,
+    ) -> Result<Vec<Message>, Error> {
+        let mut state = self.inner.lock().await;
+        let now = now_millis();
+        state.expire_due(queue, now)?;
+        state.promote_due(queue, now)?;
+        let delivery = state.queue(queue)?.plan_delivery(count, lock_secs);
+        if delivery.ids.is_empty() {
+            Self::commit(state).await?;
+            return Ok(Vec::new());
+        }
+
+        state.append(&LogEntry::Get {
+            queue: queue.to_string(),
+            ids: delivery.ids.clone(),
+            lock_until: delivery.lock_until,
+            receipts: delivery.receipts.clone(),
+        })?;
+        let claimed =
+            state
+                .queue_mut(queue)?
+                .claim(&delivery.ids, delivery.lock_until, &delivery.receipts);
+        Self::commit(state).await?;
+        Ok(claimed)
+    }
+
+    async fn stats(&self, queue: &str) -> Result<QueueStats, Error> {
+        let state = self.inner.lock().await;
+        state.queue(queue)?.stats().await
+    }
+
+    async fn delete(
+        &self,
+        queue: &str,
+        receipts: Vec<String>,
+    ) -> Result<Vec<ReceiptOutcome>, Error> {
+        let mut state = self.inner.lock().await;
+        let (ids, outcomes) =
+            state
+                .queue(queue)?
+                .settle_receipts(&receipts, now_millis(), ReceiptStatus::Deleted);
+        if ids.is_empty() {
+            return Ok(outcomes);
+        }
+
+        state.append(&LogEntry::Delete {
+            queue: queue.to_string(),
+            ids: ids.clone(),
+        })?;
+        state.queue_mut(queue)?.delete(ids).await?;
+        // Deleting the message a group waited on makes the group's next message ready
+        self.notifier.notify(queue);
+        Self::commit(state).await?;
+        Ok(outcomes)
+    }
+
+    async fn purge(&self, queue: &str) -> Result<(), Error> {
+        let mut state = self.inner.lock().await;
+        state.queue(queue)?;
+        state.append(&LogEntry::Purge {
+            queue: queue.to_string(),
+        })?;
+        state.queue_mut(queue)?.purge().await?;
+        Self::commit(state).await
+    }
+
+    async fn retry(
+        &self,
+        queue: &str,
+        receipts: Vec<String>,
+    ) -> Result<Vec<ReceiptOutcome>, Error> {
+        let mut state = self.inner.lock().await;
+        let (ids, outcomes) =
+            state
+                .queue(queue)?
+                .settle_receipts(&receipts, now_millis(), ReceiptStatus::Retried);
+        if ids.is_empty() {
+            return Ok(outcomes);
+        }
+
+        state.append(&LogEntry::Retry {
+            queue: queue.to_string(),
+            ids: ids.clone(),
+        })?;
+        state.queue_mut(queue)?.retry(ids).await?;
+        self.notifier.notify(queue);
+        Self::commit(state).await?;
+        Ok(outcomes)
+    }
+
+    async fn extend(
+        &self,
+        queue: &str,
+        receipts: Vec<String>,
+        extend_secs: u64,
+    ) -> Result<Vec<Message>, Error> {
+        let mut state = self.inner.lock().await;
+        let now = now_millis();
+        let ids = state.queue(queue)?.resolve_receipts(&receipts, now)?;
+
+        state.append(&LogEntry::Extend {
+            queue: queue.to_string(),
+            ids: ids.clone(),
+            extend_secs,
+            now,
+        })?;
+        let extended = state
+            .queue_mut(queue)?
+            .extend(&ids, extend_secs, now)
+            .await?;
+        Self::commit(state).await?;
+        Ok(extended)
+    }
+
+    async fn reap_expired(&self) -> Result<ReapResult, Error> {
+        let mut state = self.inner.lock().await;
+        let mut total = ReapResult::default();
+
+        let names: Vec<String> = state.queues.keys().cloned().collect();
+        for name in names {
+            let now = now_millis();
+            total.expired += state.expire_due(&name, now)?;
+            let promoted = state.promote_due(&name, now)?;
+            total.promoted += promoted;
+            let (to_retry, to_remove) = state.queue(&name)?.collect_expired();
+
+            let mut retried = 0;
+            if !to_retry.is_empty() || !to_remove.is_empty() {
+                let failed_at = now_millis();
+                state.append(&LogEntry::Reap {
+                    queue: name.clone(),
+                    retried: to_retry.clone(),
+                    dead: to_remove.clone(),
+                    failed_at,
+                })?;
+                let result = state
+                    .queue_mut(&name)?
+                    .process_expired(to_retry, to_remove, failed_at)
+                    .await?;
+                retried = result.retried;
+                total.retried += result.retried;
+                total.dead += result.dead;
+            }
+
+            if promoted > 0 || retried > 0 {
+                self.notifier.notify(&name);
+            }
+        }
+
+        if state.needs_compaction() {
+            state.compact()?;
+        }
+        Self::commit(state).await?;
+        Ok(total)
+    }
+
+    async fn browse(
+        &self,
+        queue: &str,
+        message_state: Option<MessageState>,
+        after: Option<Uuid>,
+        limit: usize,
+    ) -> Result<Vec<Message>, Error> {
+        let state = self.inner.lock().await;
+        Ok(state.queue(queue)?.browse(message_state, after, limit))
+    }
+
+    async fn find(&self, queue: &str, id: String) -> Result<Option<Message>, Error> {
+        let state = self.inner.lock().await;
+        Ok(state.queue(queue)?.find(&id).map(Message::without_receipt))
+    }
+
+    async fn list_dead(&self, queue: &str, limit: usize) -> Result<Vec<DeadLetter>, Error> {
+        let state = self.inner.lock().await;
+        state.queue(queue)?.list_dead(limit).await
+    }
+
+    async fn get_dead(&self, queue: &str, id: String) -> Result<Option<DeadLetter>, Error> {
+        let state = self.inner.lock().await;
+        state.queue(queue)?.get_dead(&id).await
+    }
+
+    async fn redrive(&self, queue: &str, ids: Vec<String>) -> Result<Vec<RedriveOutcome>, Error> {
+        let mut state = self.inner.lock().await;
+        let (ids, outcomes) = state.queue(queue)?.resolve_dead_letters(&ids);
+        if ids.is_empty() {
+            return Ok(outcomes);
+        }
+
+        state.append(&LogEntry::Redrive {
+            queue: queue.to_string(),
+            ids: ids.clone(),
+        })?;
+        state.queue_mut(queue)?.redrive(ids).await?;
+        self.notifier.notify(queue);
+        Self::commit(state).await?;
+        Ok(outcomes)
+    }
+
+    async fn purge_dead(&self, queue: &str) -> Result<(), Error> {
+        let mut state = self.inner.lock().await;
+        state.queue(queue)?;
+        state.append(&LogEntry::PurgeDead {
+            queue: queue.to_string(),
+        })?;
+        state.queue_mut(queue)?.purge_dead().await?;
+        Self::commit(state).await
+    }
+
+    async fn export(&self, queue: &str) -> Result<Snapshot, Error> {
+        let state = self.inner.lock().await;
+        Ok(state.queue(queue)?.export())
+    }
+
+    async fn import(
+        &self,
+        queue: &str,
+        snapshot: Snapshot,
+        processing: ProcessingRestore,
+    ) -> Result<ImportSummary, Error> {
+        let mut state = self.inner.lock().await;
+        if !state.queues.contains_key(queue) {
+            state.create_queue(queue, snapshot.config)?;
+        }
+
+        state.append(&LogEntry::Import {
+            queue: queue.to_string(),
+            messages: snapshot.messages.clone(),
+            dead_letters: snapshot.dead_letters.clone(),
+            processing,
+        })?;
+        let summary =
+            state
+                .queue_mut(queue)?
+                .import(snapshot.messages, snapshot.dead_letters, processing);
+        self.notifier.notify(queue);
+        Self::commit(state).await?;
+        Ok(summary)
+    }
+
+    async fn flush(&self) -> Result<(), Error> {
+        let written = self.inner.lock().await.writer.sync()?;
+        written.wait().await
+    }
+
+    fn subscribe(&self, queue: &str) -> Arc<Notify> {
+        self.notifier.subscribe(queue)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::RedriveStatus;
+    use std::io::Write;
+    use std::path::PathBuf;
+    use uuid::Uuid;
+
+    struct TempLog(PathBuf);
+
+    impl TempLog {
+        fn new() -> Self {
+            TempLog(std::env::temp_dir().join(format!("tlq-{}.log", Uuid::now_v7())))
+        }
+
+        fn write(&self, entries: &[LogEntry]) {
+            let mut content = String::new();
+            for entry in entries {
+                content.push_str(&serde_json::to_string(entry).unwrap());
+                content.push('\n');
+            }
+            std::fs::write(&self.0, content).unwrap();
+        }
+    }
+
+    fn test_config(max_retries: u32) -> QueueConfig {
+        QueueConfig {
+            lock_duration_secs: 60,
+            max_retries,
+            max_message_size: 65536,
+            ttl_secs: 0,
+            dead_letter_expired: false,
+            dedup_window_secs: 300,
+        }
+    }
+
+    impl Drop for TempLog {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_replay_restores_ready_order_and_retry_count() {
+        let log = TempLog::new();
+        {
+            let storage = LogStorage::open(&log.0).await.unwrap();
+            for body in ["one", "two", "three"] {
+                storage
+                    .add(DEFAULT_QUEUE, Message::new(body.to_string()))
+                    .await
+                    .unwrap();
+            }
+            let locked = storage.get(DEFAULT_QUEUE, 2, None).await.unwrap();
+            storage
+                .retry(DEFAULT_QUEUE, vec![locked[1].receipt.clone().unwrap()])
+                .await
+                .unwrap();
+        }
+
+        let storage = LogStorage::open(&log.0).await.unwrap();
+        let stats = storage.stats(DEFAULT_QUEUE).await.unwrap();
+        assert_eq!(stats.ready, 2);
+        assert_eq!(stats.processing, 1);
+
+        let messages = storage.get(DEFAULT_QUEUE, 2, None).await.unwrap();
+        assert_eq!(messages[0].body, "three");
+        assert_eq!(messages[1].body, "two");
+        assert_eq!(messages[1].retry_count, 1);
+    }
+
+    #[tokio::test]
+    async fn test_replay_restores_lock_until() {
+        let log = TempLog::new();
+        let expired = Message::new("expired".to_string());
+        let locked = Message::new("locked".to_string());
+        log.write(&[
+            LogEntry::UpdateQueue {
+                queue: DEFAULT_QUEUE.to_string(),
+                config: test_config(3),
+            },
+            LogEntry::Add {
+                queue: DEFAULT_QUEUE.to_string(),
+                message: expired.clone(),
+            },
+            LogEntry::Add {
+                queue: DEFAULT_QUEUE.to_string(),
+                message: locked.clone(),
+            },
+            LogEntry::Get {
+                queue: DEFAULT_QUEUE.to_string(),
+                ids: vec![expired.id.to_string()],
+                lock_until: 0,
+                receipts: Vec::new(),
+            },
+            LogEntry::Get {
+                queue: DEFAULT_QUEUE.to_string(),
+                ids: vec![locked.id.to_string()],
+                lock_until: i64::MAX,
+                receipts: Vec::new(),
+            },
+        ]);
+
+        let storage = LogStorage::open(&log.0).await.unwrap();
+        let result = storage.reap_expired().await.unwrap();
+        assert_eq!(result.retried, 1);
+
+        let stats = storage.stats(DEFAULT_QUEUE).await.unwrap();
+        assert_eq!(stats.ready, 1);
+        assert_eq!(stats.processing, 1);
+    }
+
+    #[tokio::test]
+    async fn test_replay_restores_batch() {
+        let log = TempLog::new();
+        let msgs = vec![
+            Message::new("one".to_string()),
+            Message::new("two".to_string()),
+        ];
+        {
+            let storage = LogStorage::open(&log.0).await.unwrap();
+            storage.add_batch("orders", msgs.clone()).await.unwrap();
+        }
+
+        let storage = LogStorage::open(&log.0).await.unwrap();
+        let delivered = storage.get("orders", 2, None).await.unwrap();
+        assert_eq!(delivered[0].id, msgs[0].id);
+        assert_eq!(delivered[1].id, msgs[1].id);
+    }
+
+    #[tokio::test]
+    async fn test_replay_keeps_totals_but_not_rates() {
+        let log = TempLog::new();
+        {
+            let storage = LogStorage::open(&log.0).await.unwrap();
+            for body in ["one", "two"] {
+                storage
+                    .add(DEFAULT_QUEUE, Message::new(body.to_string()))
+                    .await
+                    .unwrap();
+            }
+            let locked = storage.get(DEFAULT_QUEUE, 1, None).await.unwrap();
+            storage
+                .delete(DEFAULT_QUEUE, vec![locked[0].receipt.clone().unwrap()])
+                .await
+                .unwrap();
+        }
+
+        let storage = LogStorage::open(&log.0).await.unwrap();
+        let stats = storage.stats(DEFAULT_QUEUE).await.unwrap();
+        assert_eq!(stats.enqueued, 2);
+        assert_eq!(stats.acked, 1);
+        assert_eq!(stats.enqueue_rate, 0.0);
+        assert_eq!(stats.ack_rate, 0.0);
+    }
+
+    #[tokio::test]
+    async fn test_replay_restores_dedup_index() {
+        let log = TempLog::new();
+        let mut msg = Message::new("once".to_string());
+        msg.dedup_id = Some("key".to_string());
+        {
+            let storage = LogStorage::open(&log.0).await.unwrap();
+            storage.add(DEFAULT_QUEUE, msg.clone()).await.unwrap();
+        }
+
+        let storage = LogStorage::open(&log.0).await.unwrap();
+        let mut retry = Message::new("twice".to_string());
+        retry.dedup_id = Some("key".to_string());
+        let added = storage.add(DEFAULT_QUEUE, retry).await.unwrap();
+        assert_eq!(added.id, msg.id);
+        assert_eq!(storage.stats(DEFAULT_QUEUE).await.unwrap().ready, 1);
+    }
+
+    #[tokio::test]
+    async fn test_replay_restores_extended_lock() {
+        let log = TempLog::new();
+        let extended = {
+            let storage = LogStorage::open(&log.0).await.unwrap();
+            storage
+                .add(DEFAULT_QUEUE, Message::new("slow".to_string()))
+                .await
+                .unwrap();
+            let locked = storage.get(DEFAULT_QUEUE, 1, None).await.unwrap();
+            storage
+                .extend(DEFAULT_QUEUE, vec![locked[0].receipt.clone().unwrap()], 600)
+                .await
+                .unwrap()
+        };
+
+        // The receipt issued before the restart still holds the lease
+        let storage = LogStorage::open(&log.0).await.unwrap();
+        let result = storage
+            .extend(DEFAULT_QUEUE, vec![extended[0].receipt.clone().unwrap()], 1)
+            .await
+            .unwrap();
+        assert_eq!(
+            result[0].lock_until,
+            Some(extended[0].lock_until.unwrap() + 1000)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_replay_applies_delete_reap_redrive_and_purge() {
+        let log = TempLog::new();
+        let first = Message::new("first".to_string());
+        let second = Message::new("second".to_string());
+        log.write(&[
+            LogEntry::UpdateQueue {
+                queue: DEFAULT_QUEUE.to_string(),
+                config: test_config(0),
+            },
+            LogEntry::Add {
+                queue: DEFAULT_QUEUE.to_string(),
+                message: first.clone(),
+            },
+            LogEntry::Add {
+                queue: DEFAULT_QUEUE.to_string(),
+                message: second.clone(),
+            },
+            LogEntry::Get {
+                queue: DEFAULT_QUEUE.to_string(),
+                ids: vec![first.id.to_string(), second.id.to_string()],
+                lock_until: 0,
+                receipts: Vec::new(),
+            },
+            LogEntry::Delete {
+                queue: DEFAULT_QUEUE.to_string(),
+                ids: vec![first.id.to_string()],
+            },
+        ]);
+
+        let failed_at = {
+            let storage = LogStorage::open(&log.0).await.unwrap();
+            let result = storage.reap_expired().await.unwrap();
+            assert_eq!(result.dead, 1);
+            let dead = storage
+                .get_dead(DEFAULT_QUEUE, second.id.to_string())
+                .await
+                .unwrap();
+            dead.unwrap().failed_at
+        };
+
+        {
+            let storage = LogStorage::open(&log.0).await.unwrap();
+            let stats = storage.stats(DEFAULT_QUEUE).await.unwrap();
+            assert_eq!(stats.ready, 0);
+            assert_eq!(stats.processing, 0);
+            assert_eq!(stats.dead, 1);
+
+            let dead = storage
+                .get_dead(DEFAULT_QUEUE, second.id.to_string())
+                .await
+                .unwrap();
+            assert_eq!(dead.unwrap().failed_at, failed_at);
+            let outcomes = storage
+                .redrive(DEFAULT_QUEUE, vec![second.id.to_string()])
+                .await
+                .unwrap();
+            assert_eq!(outcomes[0].status, RedriveStatus::Redriven);
+        }
+
+        let storage = LogStorage::open(&log.0).await.unwrap();
+        let stats = storage.stats(DEFAULT_QUEUE).await.unwrap();
+        assert_eq!(stats.ready, 1);
+        assert_eq!(stats.dead, 0);
+
+        storage.purge(DEFAULT_QUEUE).await.unwrap();
+        drop(storage);
+
+        let storage = LogStorage::open(&log.0).await.unwrap();
+        assert_eq!(storage.stats(DEFAULT_QUEUE).await.unwrap().ready, 0);
+    }
+
+    #[tokio::test]
+    async fn test_replay_restores_scheduled_and_promoted_messages() {
+        let log = TempLog::new();
+        let mut due = Message::new("due".to_string());
+        due.schedule(1, 0);
+        let mut later = Message::new("later".to_string());
+        later.schedule(i64::MAX, 0);
+        {
+            let storage = LogStorage::open(&log.0).await.unwrap();
+            storage.add(DEFAULT_QUEUE, due.clone()).await.unwrap();
+            storage.add(DEFAULT_QUEUE, later.clone()).await.unwrap();
+
+            let messages = storage.get(DEFAULT_QUEUE, 10, None).await.unwrap();
+            assert_eq!(messages.len(), 1);
+            assert_eq!(messages[0].id, due.id);
+        }
+
+        let storage = LogStorage::open(&log.0).await.unwrap();
+        let stats = storage.stats(DEFAULT_QUEUE).await.unwrap();
+        assert_eq!(stats.ready, 0);
+        assert_eq!(stats.processing, 1);
+        assert_eq!(stats.scheduled, 1);
+    }
+
+    #[tokio::test]
+    async fn test_replay_restores_expired_messages() {
+        let log = TempLog::new();
+        let mut stale = Message::new("stale".to_string());
+        stale.expires_at = Some(1);
+        {
+            let storage = LogStorage::open(&log.0).await.unwrap();
+            storage
+                .update_queue_config(
+                    DEFAULT_QUEUE,
+                    QueueConfigUpdate {
+                        dead_letter_expired: Some(true),
+                        ..Default::default()
+                    },
+                )
+                .await
+                .unwrap();
+            storage.add(DEFAULT_QUEUE, stale.clone()).await.unwrap();
+            storage
+                .add(DEFAULT_QUEUE, Message::new("fresh".to_string()))
+                .await
+                .unwrap();
+
+            let result = storage.reap_expired().await.unwrap();
+            assert_eq!(result.expired, 1);
+        }
+
+        let storage = LogStorage::open(&log.0).await.unwrap();
+        let stats = storage.stats(DEFAULT_QUEUE).await.unwrap();
+        assert_eq!(stats.ready, 1);
+        assert_eq!(stats.expired, 1);
+        let dead = storage
+            .get_dead(DEFAULT_QUEUE, stale.id.to_string())
+            .await
+            .unwrap();
+        assert_eq!(dead.unwrap().reason, crate::types::DeadReason::Expired);
+    }
+
+    #[tokio::test]
+    async fn test_replay_restores_named_queues() {
+        let log = TempLog::new();
+        {
+            let storage = LogStorage::open(&log.0).await.unwrap();
+            storage.create_queue("empty", test_config(5)).await.unwrap();
+            storage
+                .create_queue("removed", test_config(3))
+                .await
+                .unwrap();
+            storage
+                .add("orders", Message::new("order".to_string()))
+                .await
+                .unwrap();
+            storage.delete_queue("removed").await.unwrap();
+        }
+
+        let storage = LogStorage::open(&log.0).await.unwrap();
+        let queues = storage.list_queues().await.unwrap();
+        let names: Vec<&str> = queues.iter().map(|q| q.name.as_str()).collect();
+        assert_eq!(names, vec![DEFAULT_QUEUE, "empty", "orders"]);
+        assert_eq!(queues[1].config, test_config(5));
+        assert_eq!(storage.stats("orders").await.unwrap().ready, 1);
+        assert_eq!(storage.stats(DEFAULT_QUEUE).await.unwrap().ready, 0);
+    }
+
+    #[tokio::test]
+    async fn test_replay_restores_import() {
+        let log = TempLog::new();
+        let mut locked = Message::new("locked".to_string());
+        locked.state = MessageState::Processing;
+        locked.lock_until = Some(i64::MAX);
+        let snapshot = Snapshot {
+            config: test_config(5),
+            messages: vec![Message::new("ready".to_string()), locked],
+            dead_letters: Vec::new(),
+        };
+        {
+            let storage = LogStorage::open(&log.0).await.unwrap();
+            let summary = storage
+                .import("orders", snapshot, ProcessingRestore::Keep)
+                .await
+                .unwrap();
+            assert_eq!(summary.imported, 2);
+        }
+
+        let storage = LogStorage::open(&log.0).await.unwrap();
+        assert_eq!(
+            storage.queue_config("orders").await.unwrap(),
+            Some(test_config(5))
+        );
+        let stats = storage.stats("orders").await.unwrap();
+        assert_eq!(stats.ready, 1);
+        assert_eq!(stats.processing, 1);
+    }
+
+    #[tokio::test]
+    async fn test_replay_restores_queue_config() {
+        let log = TempLog::new();
+        {
+            let storage = LogStorage::open(&log.0).await.unwrap();
+            storage
+                .update_queue_config(
+                    DEFAULT_QUEUE,
+                    QueueConfigUpdate {
+                        lock_duration_secs: Some(900),
+                        ..Default::default()
+                    },
+                )
+                .await
+                .unwrap();
+        }
+
+        let storage = LogStorage::open(&log.0).await.unwrap();
+        let config = storage.queue_config(DEFAULT_QUEUE).await.unwrap().unwrap();
+        assert_eq!(config.lock_duration_secs, 900);
+    }
+
+    #[tokio::test]
+    async fn test_compaction_keeps_state_and_shrinks_log() {
+        let log = TempLog::new();
+        {
+            let storage = LogStorage::open(&log.0).await.unwrap();
+            storage.create_queue("empty", test_config(5)).await.unwrap();
+            for body in ["one", "two", "three"] {
+                storage
+                    .add(DEFAULT_QUEUE, Message::new(body.to_string()))
+                    .await
+                    .unwrap();
+            }
+            let delivered = storage.get(DEFAULT_QUEUE, 2, None).await.unwrap();
+            let receipts = vec![delivered[0].receipt.clone().unwrap()];
+            storage.retry(DEFAULT_QUEUE, receipts).await.unwrap();
+
+            storage.compact().await.unwrap();
+            let content = std::fs::read_to_string(&log.0).unwrap();
+            assert_eq!(content.lines().count(), 2);
+
+            // Entries after the compaction are appended to the compacted log
+            storage
+                .add(DEFAULT_QUEUE, Message::new("four".to_string()))
+                .await
+                .unwrap();
+        }
+
+        let storage = LogStorage::open(&log.0).await.unwrap();
+        assert_eq!(
+            storage.queue_config("empty").await.unwrap(),
+            Some(test_config(5))
+        );
+        let stats = storage.stats(DEFAULT_QUEUE).await.unwrap();
+        assert_eq!(stats.ready, 3);
+        assert_eq!(stats.processing, 1);
+        assert_eq!(stats.enqueued, 4);
+        assert_eq!(stats.retried, 1);
+
+        let bodies: Vec<String> = storage
+            .get(DEFAULT_QUEUE, 3, None)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|message| message.body)
+            .collect();
+        assert_eq!(bodies, vec!["three", "one", "four"]);
+    }
+
+    #[tokio::test]
+    async fn test_torn_tail_is_discarded() {
+        let log = TempLog::new();
+        {
+            let storage = LogStorage::open(&log.0).await.unwrap();
+            storage
+                .add(DEFAULT_QUEUE, Message::new("kept".to_string()))
+                .await
+                .unwrap();
+        }
+        let mut file = OpenOptions::new().append(true).open(&log.0).unwrap();
+        file.write_all(b"{\"op\":\"add\",\"mess").unwrap();
+        drop(file);
+
+        let storage = LogStorage::open(&log.0).await.unwrap();
+        storage
+            .add(DEFAULT_QUEUE, Message::new("after".to_string()))
+            .await
+            .unwrap();
+        drop(storage);
+
+        let storage = LogStorage::open(&log.0).await.unwrap();
+        assert_eq!(storage.stats(DEFAULT_QUEUE).await.unwrap().ready, 2);
+    }
+
+    #[tokio::test]
+    async fn test_failed_get_leaves_messages_ready() {
+        let log = TempLog::new();
+        let mut storage = LogStorage::open(&log.0).await.unwrap();
+        storage
+            .add(DEFAULT_QUEUE, Message::new("one".to_string()))
+            .await
+            .unwrap();
+
+        storage.inner.get_mut().writer.fail();
+        assert!(storage.get(DEFAULT_QUEUE, 1, None).await.is_err());
+
+        let stats = storage.stats(DEFAULT_QUEUE).await.unwrap();
+        assert_eq!(stats.ready, 1);
+        assert_eq!(stats.processing, 0);
+    }
+
+    #[tokio::test]
+    async fn test_failed_write_refuses_changes_until_restart() {
+        let log = TempLog::new();
+        {
+            let mut storage = LogStorage::open(&log.0).await.unwrap();
+            for body in ["one", "two"] {
+                storage
+                    .add(DEFAULT_QUEUE, Message::new(body.to_string()))
+                    .await
+                    .unwrap();
+            }
+
+            // A read-only handle makes the next write fail after the queues in
+            // memory have taken the change
+            let file = File::open(&log.0).unwrap();
+            storage.inner.get_mut().writer =
+                LogWriter::spawn(&log.0, file, config::StorageSync::Never).unwrap();
+            assert!(matches!(
+                storage.get(DEFAULT_QUEUE, 1, None).await,
+                Err(Error::Storage(_))
+            ));
+
+            // Reads still show the unlogged delivery, but no change is accepted
+            let stats = storage.stats(DEFAULT_QUEUE).await.unwrap();
+            assert_eq!(stats.ready, 1);
+            assert_eq!(stats.processing, 1);
+            assert!(matches!(
+                storage
+                    .add(DEFAULT_QUEUE, Message::new("three".to_string()))
+                    .await,
+                Err(Error::Storage(_))
+            ));
+            assert!(matches!(
+                storage.get(DEFAULT_QUEUE, 1, None).await,
+                Err(Error::Storage(_))
+            ));
+            assert!(storage.purge(DEFAULT_QUEUE).await.is_err());
+            let stats = storage.stats(DEFAULT_QUEUE).await.unwrap();
+            assert_eq!(stats.ready, 1);
+            assert_eq!(stats.processing, 1);
+        }
+
+        // Replaying the log on restart drops the changes that were never written
+        let storage = LogStorage::open(&log.0).await.unwrap();
+        let stats = storage.stats(DEFAULT_QUEUE).await.unwrap();
+        assert_eq!(stats.ready, 2);
+        assert_eq!(stats.processing, 0);
+        let bodies: Vec<String> = storage
+            .browse(DEFAULT_QUEUE, None, None, 10)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|message| message.body)
+            .collect();
+        assert_eq!(bodies, vec!["one", "two"]);
+    }
+
+    #[tokio::test]
+    async fn test_corrupted_entry_fails_to_open() {
+        let log = TempLog::new();
+        std::fs::write(&log.0, "not json\n{\"op\":\"purge\"}\n").unwrap();
+
+        let result = LogStorage::open(&log.0).await;
+        assert_eq!(result.err().unwrap().kind(), io::ErrorKind::InvalidData);
+    }
+}
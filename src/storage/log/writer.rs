@@ -0,0 +1,267 @@
+use crate::config::StorageSync;
+use crate::errors::Error;
+use crate::storage::replace_file;
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use tokio::sync::oneshot;
+
+/// Writes the storage log on a dedicated thread, so file I/O never blocks the
+/// async runtime. Writes queued while the thread is busy are handled as one
+/// batch and synced to disk together.
+///
+/// A failed write fails every request of its batch. The log then no longer
+/// matches the queues in memory, so the writer refuses everything after it
+/// until the log is replayed on restart.
+pub(crate) struct LogWriter {
+    requests: mpsc::Sender<Request>,
+    failed: Arc<AtomicBool>,
+    len: Arc<AtomicU64>,
+}
+
+enum Command {
+    /// Append encoded entries to the log
+    Append(Vec<u8>),
+    /// Replace the whole log with encoded entries
+    Rewrite(Vec<u8>),
+    /// Force everything written so far to disk, whatever the sync policy
+    Sync,
+}
+
+struct Request {
+    command: Command,
+    done: oneshot::Sender<Result<(), Error>>,
+}
+
+/// Resolves once the write it was returned for reached the log, and the disk
+/// if the sync policy asks for it.
+pub(crate) struct Written(oneshot::Receiver<Result<(), Error>>);
+
+impl Written {
+    pub(crate) async fn wait(self) -> Result<(), Error> {
+        self.0.await.unwrap_or_else(|_| Err(stopped()))
+    }
+}
+
+impl LogWriter {
+    /// Starts the writer thread, appending to `file`, the open log at `path`.
+    pub(crate) fn spawn(path: &Path, file: File, sync: StorageSync) -> io::Result<Self> {
+        let len = Arc::new(AtomicU64::new(file.metadata()?.len()));
+        let failed = Arc::new(AtomicBool::new(false));
+        let (requests, receiver) = mpsc::channel();
+
+        let thread = WriterThread {
+            path: path.to_path_buf(),
+            file,
+            sync,
+            len: len.clone(),
+            failed: failed.clone(),
+        };
+        thread::Builder::new()
+            .name("tlq-log-writer".to_string())
+            .spawn(move || thread.run(receiver))?;
+
+        Ok(LogWriter {
+            requests,
+            failed,
+            len,
+        })
+    }
+
+    /// Queues an encoded entry for appending. Fails without queueing anything
+    /// if an earlier write failed.
+    pub(crate) fn append(&self, line: Vec<u8>) -> Result<Written, Error> {
+        self.send(Command::Append(line))
+    }
+
+    /// Queues replacing the log with `contents` once the writes queued before are done.
+    pub(crate) fn rewrite(&self, contents: Vec<u8>) -> Result<Written, Error> {
+        self.send(Command::Rewrite(contents))
+    }
+
+    /// Queues syncing the log to disk.
+    pub(crate) fn sync(&self) -> Result<Written, Error> {
+        self.send(Command::Sync)
+    }
+
+    /// Size of the log file, including queued writes already handled.
+    pub(crate) fn len(&self) -> u64 {
+        self.len.load(Ordering::Acquire)
+    }
+
+    fn send(&self, command: Command) -> Result<Written, Error> {
+        if self.failed.load(Ordering::Acquire) {
+            return Err(failed());
+        }
+
+        let (done, written) = oneshot::channel();
+        self.requests
+            .send(Request { command, done })
+            .map_err(|_| stopped())?;
+        Ok(Written(written))
+    }
+
+    #[cfg(test)]
+    pub(crate) fn fail(&self) {
+        self.failed.store(true, Ordering::Release);
+    }
+}
+
+fn failed() -> Error {
+    Error::Storage("Storage log is unusable after a failed write; restart to recover".to_string())
+}
+
+fn stopped() -> Error {
+    Error::Storage("Storage log writer stopped".to_string())
+}
+
+struct WriterThread {
+    path: PathBuf,
+    file: File,
+    sync: StorageSync,
+    len: Arc<AtomicU64>,
+    failed: Arc<AtomicBool>,
+}
+
+impl WriterThread {
+    /// Handles requests until the [`LogWriter`] is dropped.
+    fn run(mut self, requests: mpsc::Receiver<Request>) {
+        while let Ok(first) = requests.recv() {
+            let batch: Vec<Request> = std::iter::once(first).chain(requests.try_iter()).collect();
+
+            let result = if self.failed.load(Ordering::Acquire) {
+                Err(failed())
+            } else {
+                self.write(&batch).map_err(|e| {
+                    self.failed.store(true, Ordering::Release);
+                    Error::Storage(format!("Failed to write storage log: {e}"))
+                })
+            };
+
+            for request in batch {
+                // The caller may have given up waiting
+                let _ = request.done.send(result.clone());
+            }
+        }
+    }
+
+    fn write(&mut self, batch: &[Request]) -> io::Result<()> {
+        let mut dirty = false;
+        let mut sync = false;
+        for request in batch {
+            match &request.command {
+                Command::Append(line) => {
+                    self.file.write_all(line)?;
+                    self.len.fetch_add(line.len() as u64, Ordering::AcqRel);
+                    dirty = true;
+                }
+                Command::Rewrite(contents) => {
+                    self.file = replace_file(&self.path, contents)?;
+                    self.len.store(contents.len() as u64, Ordering::Release);
+                    dirty = false;
+                }
+                Command::Sync => sync = true,
+            }
+        }
+
+        if sync || (dirty && self.sync == StorageSync::Always) {
+            self.file.sync_data()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use uuid::Uuid;
+
+    fn temp_path() -> PathBuf {
+        std::env::temp_dir().join(format!("tlq-{}.log", Uuid::now_v7()))
+    }
+
+    #[tokio::test]
+    async fn test_appends_in_order_and_rewrites() {
+        let path = temp_path();
+        let file = File::create(&path).unwrap();
+        let writer = LogWriter::spawn(&path, file, StorageSync::Always).unwrap();
+
+        let first = writer.append(b"a\n".to_vec()).unwrap();
+        let second = writer.append(b"b\n".to_vec()).unwrap();
+        first.wait().await.unwrap();
+        second.wait().await.unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "a\nb\n");
+        assert_eq!(writer.len(), 4);
+
+        writer.rewrite(b"c\n".to_vec()).unwrap();
+        writer
+            .append(b"d\n".to_vec())
+            .unwrap()
+            .wait()
+            .await
+            .unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "c\nd\n");
+        assert_eq!(writer.len(), 4);
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_failed_write_refuses_later_writes() {
+        let path = temp_path();
+        File::create(&path).unwrap();
+        // A read-only handle makes every write fail
+        let file = File::open(&path).unwrap();
+        let writer = LogWriter::spawn(&path, file, StorageSync::Never).unwrap();
+
+        let written = writer.append(b"a\n".to_vec()).unwrap();
+        assert!(matches!(written.wait().await, Err(Error::Storage(_))));
+        assert!(matches!(
+            writer.append(b"b\n".to_vec()),
+            Err(Error::Storage(_))
+        ));
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_failure_partway_through_batch_fails_whole_batch() {
+        let path = temp_path();
+        let failed = Arc::new(AtomicBool::new(false));
+        // Rewriting fails, as the log path is taken to be in a directory that is a file
+        let thread = WriterThread {
+            path: path.join("log"),
+            file: File::create(&path).unwrap(),
+            sync: StorageSync::Never,
+            len: Arc::new(AtomicU64::new(0)),
+            failed: failed.clone(),
+        };
+
+        // Queue every request before the thread runs so they form one batch
+        let (requests, receiver) = mpsc::channel();
+        let mut written = Vec::new();
+        for command in [
+            Command::Append(b"a\n".to_vec()),
+            Command::Rewrite(b"b\n".to_vec()),
+            Command::Append(b"c\n".to_vec()),
+        ] {
+            let (done, receiver) = oneshot::channel();
+            requests.send(Request { command, done }).unwrap();
+            written.push(Written(receiver));
+        }
+        drop(requests);
+        thread.run(receiver);
+
+        for written in written {
+            assert!(matches!(written.wait().await, Err(Error::Storage(_))));
+        }
+        assert!(failed.load(Ordering::Acquire));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "a\n");
+
+        fs::remove_file(&path).unwrap();
+    }
+}
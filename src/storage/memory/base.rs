@@ -1,91 +1,508 @@
-use crate::config;
-use crate::types::{Message, MessageState, QueueStats, ReapResult};
-use std::collections::HashMap;
-use std::time::{SystemTime, UNIX_EPOCH};
-
-fn now_millis() -> i64 {
-    SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap()
-        .as_millis() as i64
+use super::ready::ReadyQueue;
+use super::throughput::Throughput;
+use crate::errors::Error;
+use crate::time::{now_millis, secs_to_millis};
+use crate::types::{
+    DeadLetter, DeadReason, ImportSummary, Message, MessageState, ProcessingRestore, QueueConfig,
+    QueueStats, ReapResult, ReceiptOutcome, ReceiptStatus, RedriveOutcome, RedriveStatus, Snapshot,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::ops::Bound;
+use uuid::Uuid;
+
+/// Messages picked for a delivery, with the lock and receipts they get
+pub(crate) struct Delivery {
+    pub(crate) ids: Vec<String>,
+    pub(crate) lock_until: i64,
+    pub(crate) receipts: Vec<String>,
+}
+
+/// The complete state of a queue, from which [`BaseMemoryStorage::restore`]
+/// rebuilds it exactly, down to delivery order and deduplication windows.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct Checkpoint {
+    config: QueueConfig,
+    /// Ready messages in delivery order
+    ready: Vec<Message>,
+    scheduled: Vec<Message>,
+    processing: Vec<Message>,
+    dead_letters: Vec<DeadLetter>,
+    /// Messages added with a `dedup_id`, with the end of their deduplication window
+    dedup: Vec<(i64, Message)>,
+    expired: usize,
+    enqueued: u64,
+    acked: u64,
+    retried: u64,
 }
 
 pub struct BaseMemoryStorage {
-    queue: Vec<Message>,
-    processing: HashMap<String, Message>,
-    dead_count: usize,
+    config: QueueConfig,
+    queue: ReadyQueue,
+    /// Processing messages by ID; hyphenated UUIDs sort as strings in ID order
+    processing: BTreeMap<String, Message>,
+    /// Scheduled messages ordered by delivery time, then by ID
+    scheduled: BTreeMap<(i64, Uuid), Message>,
+    /// Delivery times of scheduled messages by ID
+    scheduled_ids: BTreeMap<Uuid, i64>,
+    /// Expiry times of waiting messages, mapped to their delivery time if scheduled.
+    /// Entries of messages that were consumed in the meantime are dropped lazily
+    expiring: BTreeMap<(i64, Uuid), Option<i64>>,
+    dead_letters: BTreeMap<String, DeadLetter>,
+    /// Number of messages expired since the queue was created
+    expired: usize,
+    /// Messages added with a `dedup_id`, with the end of their deduplication window
+    dedup: HashMap<String, (i64, Message)>,
+    /// Deduplication keys ordered by the end of their window
+    dedup_expiry: BTreeSet<(i64, String)>,
+    throughput: Throughput,
 }
 
 impl BaseMemoryStorage {
-    pub(crate) fn new() -> Self {
+    pub(crate) fn new(config: QueueConfig) -> Self {
         BaseMemoryStorage {
-            queue: Vec::new(),
-            processing: HashMap::new(),
-            dead_count: 0,
+            config,
+            queue: ReadyQueue::new(),
+            processing: BTreeMap::new(),
+            scheduled: BTreeMap::new(),
+            scheduled_ids: BTreeMap::new(),
+            expiring: BTreeMap::new(),
+            dead_letters: BTreeMap::new(),
+            expired: 0,
+            dedup: HashMap::new(),
+            dedup_expiry: BTreeSet::new(),
+            throughput: Throughput::default(),
         }
     }
 
-    pub(crate) async fn add(&mut self, msg: Message) -> Result<(), String> {
-        self.queue.push(msg);
+    pub(crate) fn config(&self) -> QueueConfig {
+        self.config
+    }
+
+    pub(crate) fn set_config(&mut self, config: QueueConfig) {
+        self.config = config;
+    }
+
+    pub(crate) async fn add(&mut self, msg: Message) -> Result<(), Error> {
+        self.throughput.record_enqueued(now_millis(), 1);
+        self.insert(msg);
         Ok(())
     }
 
-    pub(crate) async fn get(&mut self, count: usize) -> Result<Vec<Message>, String> {
-        let count = count.min(self.queue.len());
-        let lock_until = now_millis() + (config::config().lock_duration_secs * 1000) as i64;
-        let mut messages: Vec<Message> = self.queue.drain(0..count).collect();
-        for message in &mut messages {
+    /// Stores a waiting message: scheduled if it has a delivery time ahead, ready otherwise.
+    fn insert(&mut self, msg: Message) {
+        self.track_dedup(&msg);
+        self.place(msg);
+    }
+
+    fn place(&mut self, msg: Message) {
+        match (msg.state, msg.deliver_at) {
+            (MessageState::Scheduled, Some(deliver_at)) => {
+                self.track_expiry(&msg);
+                self.scheduled_ids.insert(msg.id, deliver_at);
+                self.scheduled.insert((deliver_at, msg.id), msg);
+            }
+            _ => self.make_ready(msg),
+        }
+    }
+
+    /// Appends a message to the ready queue.
+    fn make_ready(&mut self, mut message: Message) {
+        message.state = MessageState::Ready;
+        self.track_expiry(&message);
+        self.queue.push(message);
+    }
+
+    /// Returns a message to the ready queue after a failed delivery. Grouped
+    /// messages go back ahead of their priority to keep their place in the group.
+    fn requeue(&mut self, mut message: Message) {
+        if message.group_id.is_none() {
+            return self.make_ready(message);
+        }
+
+        message.state = MessageState::Ready;
+        self.track_expiry(&message);
+        self.queue.push_front(message);
+    }
+
+    fn track_expiry(&mut self, message: &Message) {
+        if let Some(expires_at) = message.expires_at {
+            self.expiring
+                .insert((expires_at, message.id), message.deliver_at);
+        }
+    }
+
+    /// Remembers a message added with a `dedup_id` until the queue's deduplication
+    /// window, counted from the message's creation, has passed.
+    fn track_dedup(&mut self, message: &Message) {
+        let Some(dedup_id) = &message.dedup_id else {
+            return;
+        };
+        if self.config.dedup_window_secs == 0 {
+            return;
+        }
+
+        self.prune_dedup(now_millis());
+        let until = message
+            .created_at()
+            .saturating_add(secs_to_millis(self.config.dedup_window_secs));
+        if let Some((previous, _)) = self
+            .dedup
+            .insert(dedup_id.clone(), (until, message.clone()))
+        {
+            self.dedup_expiry.remove(&(previous, dedup_id.clone()));
+        }
+        self.dedup_expiry.insert((until, dedup_id.clone()));
+    }
+
+    /// Forgets deduplication keys whose window ended at or before `now`.
+    fn prune_dedup(&mut self, now: i64) {
+        while let Some((until, dedup_id)) = self.dedup_expiry.first().cloned() {
+            if until > now {
+                break;
+            }
+            self.dedup_expiry.pop_first();
+            self.dedup.remove(&dedup_id);
+        }
+    }
+
+    /// Returns the message previously added with `dedup_id`, if its
+    /// deduplication window is still open at `now`.
+    pub(crate) fn find_duplicate(&self, dedup_id: &str, now: i64) -> Option<&Message> {
+        self.dedup
+            .get(dedup_id)
+            .filter(|(until, _)| *until > now)
+            .map(|(_, message)| message)
+    }
+
+    /// Returns the delivery time of the earliest scheduled message.
+    pub(crate) fn next_delivery(&self) -> Option<i64> {
+        self.scheduled
+            .first_key_value()
+            .map(|((deliver_at, _), _)| *deliver_at)
+    }
+
+    /// Moves scheduled messages due at or before `now` to the ready queue,
+    /// in delivery order. Returns the number of promoted messages.
+    pub(crate) fn promote_due(&mut self, now: i64) -> usize {
+        let later = self
+            .scheduled
+            .split_off(&(now.saturating_add(1), Uuid::nil()));
+        let due = std::mem::replace(&mut self.scheduled, later);
+        let promoted = due.len();
+
+        for ((_, id), mut message) in due {
+            self.scheduled_ids.remove(&id);
+            message.state = MessageState::Ready;
+            self.queue.push(message);
+        }
+
+        promoted
+    }
+
+    /// Returns the earliest expiry time among waiting messages.
+    pub(crate) fn next_expiry(&self) -> Option<i64> {
+        self.expiring
+            .first_key_value()
+            .map(|((expires_at, _), _)| *expires_at)
+    }
+
+    /// Removes ready and scheduled messages whose time-to-live ran out at or
+    /// before `now`, dead-lettering them if the queue is configured to.
+    /// Returns the number of expired messages.
+    pub(crate) fn expire_due(&mut self, now: i64) -> usize {
+        let later = self
+            .expiring
+            .split_off(&(now.saturating_add(1), Uuid::nil()));
+        let due = std::mem::replace(&mut self.expiring, later);
+        let mut expired = 0;
+
+        for ((expires_at, id), deliver_at) in due {
+            // Messages retrieved or removed in the meantime are no longer waiting, and
+            // a message given a new time-to-live since, e.g. by a redrive, is not due
+            let current = |message: &Message| message.expires_at == Some(expires_at);
+            let message = if self.queue.get(&id).is_some_and(current) {
+                self.queue.remove(&id)
+            } else {
+                deliver_at
+                    .filter(|deliver_at| {
+                        self.scheduled.get(&(*deliver_at, id)).is_some_and(current)
+                    })
+                    .and_then(|deliver_at| {
+                        self.scheduled_ids.remove(&id);
+                        self.scheduled.remove(&(deliver_at, id))
+                    })
+            };
+            let Some(message) = message else {
+                continue;
+            };
+
+            expired += 1;
+            if self.config.dead_letter_expired {
+                self.add_dead_letter(message, DeadReason::Expired, now);
+            }
+        }
+
+        self.expired += expired;
+        expired
+    }
+
+    /// Locks up to `count` ready messages for `lock_secs`, or for the queue's
+    /// lock duration if not given.
+    pub(crate) async fn get(
+        &mut self,
+        count: usize,
+        lock_secs: Option<u64>,
+    ) -> Result<Vec<Message>, Error> {
+        let delivery = self.plan_delivery(count, lock_secs);
+        Ok(self.claim(&delivery.ids, delivery.lock_until, &delivery.receipts))
+    }
+
+    /// Picks up to `count` messages to deliver and the lease they would get,
+    /// without changing anything. [`BaseMemoryStorage::claim`] carries it out.
+    pub(crate) fn plan_delivery(&self, count: usize, lock_secs: Option<u64>) -> Delivery {
+        let lock_secs = lock_secs.unwrap_or(self.config.lock_duration_secs);
+        let (ids, receipts) = self
+            .queue
+            .deliverable()
+            .take(count)
+            .map(|message| (message.id.to_string(), message.new_receipt()))
+            .unzip();
+
+        Delivery {
+            ids,
+            lock_until: now_millis().saturating_add(secs_to_millis(lock_secs)),
+            receipts,
+        }
+    }
+
+    /// Moves the given ready messages to processing with a fixed lock expiry
+    /// and their receipts, returning the claimed messages. Also replays a
+    /// previously recorded `get` without re-deriving the lock time.
+    pub(crate) fn claim(
+        &mut self,
+        ids: &[String],
+        lock_until: i64,
+        receipts: &[String],
+    ) -> Vec<Message> {
+        let mut claimed = Vec::with_capacity(ids.len());
+        for (index, id) in ids.iter().enumerate() {
+            let Some(mut message) = Uuid::parse_str(id)
+                .ok()
+                .and_then(|id| self.queue.remove(&id))
+            else {
+                continue;
+            };
+
             message.state = MessageState::Processing;
             message.lock_until = Some(lock_until);
+            message.receipt = receipts.get(index).cloned();
+            claimed.push(message.clone());
+            self.lock(message);
+        }
+        claimed
+    }
+
+    /// Puts a message into processing, holding back the rest of its group.
+    fn lock(&mut self, message: Message) {
+        if let Some(group_id) = &message.group_id {
+            self.queue.lock_group(group_id);
+        }
+        self.processing.insert(message.id.to_string(), message);
+    }
+
+    /// Takes a message out of processing, releasing its group.
+    fn unlock(&mut self, id: &str) -> Option<Message> {
+        let message = self.processing.remove(id)?;
+        if let Some(group_id) = &message.group_id {
+            self.queue.unlock_group(group_id);
+        }
+        Some(message)
+    }
+
+    /// Checks a receipt against the current lease of its message. Returns the
+    /// message ID if the lease is still held, or why it is not.
+    fn lease(&self, receipt: &str, now: i64) -> Result<String, ReceiptStatus> {
+        let Some(id) = Message::receipt_id(receipt) else {
+            return Err(ReceiptStatus::NotFound);
+        };
+
+        match self.processing.get(&id.to_string()) {
+            Some(message)
+                if message.receipt.as_deref() == Some(receipt)
+                    && message.lock_until.is_some_and(|lock| lock > now) =>
+            {
+                Ok(message.id.to_string())
+            }
+            Some(_) => Err(ReceiptStatus::LockExpired),
+            None if self.contains(&id) => Err(ReceiptStatus::NotProcessing),
+            None => Err(ReceiptStatus::NotFound),
+        }
+    }
+
+    /// Whether a message with the given ID is waiting, scheduled or dead.
+    fn contains(&self, id: &Uuid) -> bool {
+        self.queue.contains(id)
+            || self.scheduled_ids.contains_key(id)
+            || self.dead_letters.contains_key(&id.to_string())
+    }
+
+    /// Resolves receipt handles to the IDs of the processing messages they were
+    /// issued for. Fails if any receipt does not belong to a current, unexpired lease.
+    pub(crate) fn resolve_receipts(
+        &self,
+        receipts: &[String],
+        now: i64,
+    ) -> Result<Vec<String>, Error> {
+        let mut ids = Vec::with_capacity(receipts.len());
+        let mut invalid = Vec::new();
+
+        for receipt in receipts {
+            match self.lease(receipt, now) {
+                Ok(id) => ids.push(id),
+                Err(_) => invalid.push(receipt),
+            }
+        }
 
-            self.processing
-                .insert(message.id.to_string(), message.clone());
+        if !invalid.is_empty() {
+            return Err(Error::Conflict(format!(
+                "Receipts expired or no longer valid: {invalid:?}"
+            )));
         }
 
-        Ok(messages)
+        Ok(ids)
+    }
+
+    /// Reports the outcome of each receipt of a delete or retry, assuming the
+    /// held leases end up `settled`. Returns the IDs of the held leases alongside.
+    pub(crate) fn settle_receipts(
+        &self,
+        receipts: &[String],
+        now: i64,
+        settled: ReceiptStatus,
+    ) -> (Vec<String>, Vec<ReceiptOutcome>) {
+        let mut ids = Vec::with_capacity(receipts.len());
+        let outcomes = receipts
+            .iter()
+            .map(|receipt| {
+                let status = match self.lease(receipt, now) {
+                    Ok(id) => {
+                        if !ids.contains(&id) {
+                            ids.push(id);
+                        }
+                        settled
+                    }
+                    Err(status) => status,
+                };
+
+                ReceiptOutcome {
+                    id: Message::receipt_id(receipt),
+                    receipt: receipt.clone(),
+                    status,
+                }
+            })
+            .collect();
+
+        (ids, outcomes)
     }
 
-    pub(crate) async fn stats(&self) -> Result<QueueStats, String> {
+    /// Pushes the lock of processing messages forward by `extend_secs`, counted
+    /// from the current lock expiry or from `now` if that has already passed.
+    pub(crate) async fn extend(
+        &mut self,
+        ids: &[String],
+        extend_secs: u64,
+        now: i64,
+    ) -> Result<Vec<Message>, Error> {
+        let mut extended = Vec::with_capacity(ids.len());
+        for id in ids {
+            if let Some(message) = self.processing.get_mut(id) {
+                let locked_from = message.lock_until.unwrap_or(now).max(now);
+                message.lock_until = Some(locked_from.saturating_add(secs_to_millis(extend_secs)));
+                extended.push(message.clone());
+            }
+        }
+
+        Ok(extended)
+    }
+
+    pub(crate) async fn stats(&self) -> Result<QueueStats, Error> {
+        let now = now_millis();
+        let oldest_ready_age_ms = self
+            .queue
+            .oldest()
+            .map(|message| (now - message.created_at()).max(0));
+        let bytes = self
+            .queue
+            .iter()
+            .chain(self.scheduled.values())
+            .chain(self.processing.values())
+            .map(Message::size)
+            .sum();
+
         Ok(QueueStats {
             ready: self.queue.len(),
             processing: self.processing.len(),
-            dead: self.dead_count,
+            scheduled: self.scheduled.len(),
+            dead: self.dead_letters.len(),
+            expired: self.expired,
+            oldest_ready_age_ms,
+            oldest_lock_until: self
+                .processing
+                .values()
+                .filter_map(|message| message.lock_until)
+                .min(),
+            bytes,
+            enqueued: self.throughput.enqueued,
+            acked: self.throughput.acked,
+            retried: self.throughput.retried,
+            enqueue_rate: self.throughput.enqueue_rate.per_sec(now),
+            ack_rate: self.throughput.ack_rate.per_sec(now),
         })
     }
 
-    pub(crate) async fn delete(&mut self, ids: Vec<String>) -> Result<(), String> {
+    /// Forgets the recent enqueue and ack rates, keeping the cumulative counts.
+    pub(crate) fn reset_rates(&mut self) {
+        self.throughput.reset_rates();
+    }
+
+    pub(crate) async fn delete(&mut self, ids: Vec<String>) -> Result<(), Error> {
+        let mut acked = 0;
         for id in ids {
-            self.processing.remove(&id);
+            if self.unlock(&id).is_some() {
+                acked += 1;
+            }
         }
+        self.throughput.record_acked(now_millis(), acked);
         Ok(())
     }
 
-    pub(crate) async fn purge(&mut self) -> Result<(), String> {
+    pub(crate) async fn purge(&mut self) -> Result<(), Error> {
         self.queue.clear();
         self.processing.clear();
-        self.dead_count = 0;
+        self.scheduled.clear();
+        self.scheduled_ids.clear();
+        self.expiring.clear();
+        self.dead_letters.clear();
         Ok(())
     }
 
-    pub(crate) async fn retry(&mut self, ids: Vec<String>) -> Result<(), String> {
-        let mut retried_messages = Vec::new();
-
+    pub(crate) async fn retry(&mut self, ids: Vec<String>) -> Result<(), Error> {
         for id in &ids {
-            if let Some(mut message) = self.processing.remove(id) {
+            if let Some(mut message) = self.unlock(id) {
+                self.throughput.retried += 1;
                 message.retry_count += 1;
-                message.state = MessageState::Ready;
                 message.lock_until = None;
+                message.receipt = None;
 
-                retried_messages.push(message);
+                self.requeue(message);
             }
         }
 
-        self.queue.extend(retried_messages);
-
         Ok(())
     }
 
-    pub(crate) fn collect_expired(&self, max_retries: u32) -> (Vec<String>, Vec<String>) {
+    pub(crate) fn collect_expired(&self) -> (Vec<String>, Vec<String>) {
         let now_ms = now_millis();
         let mut to_retry = Vec::new();
         let mut to_remove = Vec::new();
@@ -93,7 +510,7 @@ impl BaseMemoryStorage {
         for (id, msg) in &self.processing {
             if let Some(lock_until) = msg.lock_until {
                 if now_ms >= lock_until {
-                    if (msg.retry_count as u32) < max_retries {
+                    if (msg.retry_count as u32) < self.config.max_retries {
                         to_retry.push(id.clone());
                     } else {
                         to_remove.push(id.clone());
@@ -109,38 +526,316 @@ impl BaseMemoryStorage {
         &mut self,
         to_retry: Vec<String>,
         to_remove: Vec<String>,
-    ) -> Result<ReapResult, String> {
+        failed_at: i64,
+    ) -> Result<ReapResult, Error> {
         let retried = to_retry.len();
         let dead = to_remove.len();
         self.retry(to_retry).await?;
-        self.delete(to_remove).await?;
-        self.dead_count += dead;
-        Ok(ReapResult { retried, dead })
+        self.bury(to_remove, DeadReason::MaxRetriesExceeded, failed_at);
+        Ok(ReapResult {
+            retried,
+            dead,
+            ..Default::default()
+        })
+    }
+
+    /// Moves processing messages into the dead-letter store.
+    fn bury(&mut self, ids: Vec<String>, reason: DeadReason, failed_at: i64) {
+        for id in ids {
+            if let Some(message) = self.unlock(&id) {
+                self.add_dead_letter(message, reason, failed_at);
+            }
+        }
+    }
+
+    fn add_dead_letter(&mut self, mut message: Message, reason: DeadReason, failed_at: i64) {
+        message.state = MessageState::Dead;
+        message.lock_until = None;
+        message.receipt = None;
+        self.dead_letters.insert(
+            message.id.to_string(),
+            DeadLetter {
+                message,
+                reason,
+                failed_at,
+            },
+        );
+    }
+
+    /// Looks up a message by ID, wherever it is in the queue, including the
+    /// dead-letter queue.
+    pub(crate) fn find(&self, id: &str) -> Option<&Message> {
+        if let Some(message) = self.processing.get(id) {
+            return Some(message);
+        }
+        if let Some(dead_letter) = self.dead_letters.get(id) {
+            return Some(&dead_letter.message);
+        }
+
+        let id = Uuid::parse_str(id).ok()?;
+        self.queue.get(&id).or_else(|| {
+            let deliver_at = self.scheduled_ids.get(&id)?;
+            self.scheduled.get(&(*deliver_at, id))
+        })
+    }
+
+    /// Returns up to `limit` ready, scheduled and processing messages with an ID
+    /// greater than `after`, in ID order, optionally only those in `state`.
+    /// Receipts are left out so browsing cannot be used to settle deliveries.
+    pub(crate) fn browse(
+        &self,
+        state: Option<MessageState>,
+        after: Option<Uuid>,
+        limit: usize,
+    ) -> Vec<Message> {
+        self.select(state, after, limit)
+            .into_iter()
+            .map(Message::without_receipt)
+            .collect()
+    }
+
+    fn select(
+        &self,
+        state: Option<MessageState>,
+        after: Option<Uuid>,
+        limit: usize,
+    ) -> Vec<&Message> {
+        let wanted = |source: MessageState| state.is_none_or(|state| state == source);
+        let mut messages: Vec<&Message> = Vec::new();
+        if wanted(MessageState::Ready) {
+            messages.extend(self.queue.after(after).take(limit));
+        }
+        if wanted(MessageState::Scheduled) {
+            let scheduled = self
+                .scheduled_ids
+                .range(after_bounds(after))
+                .map(|(id, deliver_at)| &self.scheduled[&(*deliver_at, *id)]);
+            messages.extend(scheduled.take(limit));
+        }
+        if wanted(MessageState::Processing) {
+            let after = after.map(|id| id.to_string());
+            let processing = self
+                .processing
+                .range::<String, _>(after_bounds(after))
+                .map(|(_, message)| message);
+            messages.extend(processing.take(limit));
+        }
+
+        // Each state is scanned in ID order, so the page is among the first
+        // `limit` messages of each
+        messages.sort_unstable_by_key(|message| message.id);
+        messages.truncate(limit);
+        messages
+    }
+
+    /// Copies the configuration, messages and dead letters of the queue.
+    pub(crate) fn export(&self) -> Snapshot {
+        Snapshot {
+            config: self.config,
+            messages: self
+                .select(None, None, usize::MAX)
+                .into_iter()
+                .cloned()
+                .collect(),
+            dead_letters: self.dead_letters.values().cloned().collect(),
+        }
+    }
+
+    /// Restores messages and dead letters from a snapshot, keeping the queue's
+    /// configuration. Entries whose ID is already in the queue are skipped.
+    pub(crate) fn import(
+        &mut self,
+        messages: Vec<Message>,
+        dead_letters: Vec<DeadLetter>,
+        processing: ProcessingRestore,
+    ) -> ImportSummary {
+        let mut summary = ImportSummary::default();
+        for mut message in messages {
+            if self.find(&message.id.to_string()).is_some() {
+                summary.skipped += 1;
+                continue;
+            }
+
+            match (message.state, processing) {
+                (MessageState::Processing, ProcessingRestore::Keep) => {
+                    self.track_dedup(&message);
+                    self.lock(message);
+                }
+                (MessageState::Processing, ProcessingRestore::Requeue) => {
+                    message.lock_until = None;
+                    message.receipt = None;
+                    self.insert(message);
+                }
+                (MessageState::Ready | MessageState::Scheduled, _) => self.insert(message),
+                _ => {
+                    summary.skipped += 1;
+                    continue;
+                }
+            }
+            summary.imported += 1;
+        }
+
+        for dead_letter in dead_letters {
+            let id = dead_letter.message.id.to_string();
+            if self.find(&id).is_some() {
+                summary.skipped += 1;
+                continue;
+            }
+            self.dead_letters.insert(id, dead_letter);
+            summary.dead_letters += 1;
+        }
+
+        summary
+    }
+
+    /// Captures the complete state of the queue.
+    pub(crate) fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            config: self.config,
+            ready: self.queue.iter().cloned().collect(),
+            scheduled: self.scheduled.values().cloned().collect(),
+            processing: self.processing.values().cloned().collect(),
+            dead_letters: self.dead_letters.values().cloned().collect(),
+            dedup: self.dedup.values().cloned().collect(),
+            expired: self.expired,
+            enqueued: self.throughput.enqueued,
+            acked: self.throughput.acked,
+            retried: self.throughput.retried,
+        }
+    }
+
+    /// Rebuilds a queue from a [`BaseMemoryStorage::checkpoint`].
+    pub(crate) fn restore(checkpoint: Checkpoint) -> Self {
+        let mut storage = BaseMemoryStorage::new(checkpoint.config);
+        for message in checkpoint.ready.into_iter().chain(checkpoint.scheduled) {
+            storage.place(message);
+        }
+        for message in checkpoint.processing {
+            storage.lock(message);
+        }
+        for dead_letter in checkpoint.dead_letters {
+            storage
+                .dead_letters
+                .insert(dead_letter.message.id.to_string(), dead_letter);
+        }
+        for (until, message) in checkpoint.dedup {
+            let Some(dedup_id) = message.dedup_id.clone() else {
+                continue;
+            };
+            storage.dedup_expiry.insert((until, dedup_id.clone()));
+            storage.dedup.insert(dedup_id, (until, message));
+        }
+        storage.expired = checkpoint.expired;
+        storage.throughput.enqueued = checkpoint.enqueued;
+        storage.throughput.acked = checkpoint.acked;
+        storage.throughput.retried = checkpoint.retried;
+        storage
+    }
+
+    pub(crate) async fn list_dead(&self, limit: usize) -> Result<Vec<DeadLetter>, Error> {
+        Ok(self.dead_letters.values().take(limit).cloned().collect())
+    }
+
+    pub(crate) async fn get_dead(&self, id: &str) -> Result<Option<DeadLetter>, Error> {
+        Ok(self.dead_letters.get(id).cloned())
+    }
+
+    /// Looks up the dead letters of a redrive request. Returns the IDs to redrive,
+    /// each once, and the outcome for every requested ID.
+    pub(crate) fn resolve_dead_letters(
+        &self,
+        ids: &[String],
+    ) -> (Vec<String>, Vec<RedriveOutcome>) {
+        let mut found: Vec<String> = Vec::with_capacity(ids.len());
+        let outcomes = ids
+            .iter()
+            .map(|id| {
+                let status = if self.dead_letters.contains_key(id) && !found.contains(id) {
+                    found.push(id.clone());
+                    RedriveStatus::Redriven
+                } else {
+                    RedriveStatus::NotFound
+                };
+                RedriveOutcome {
+                    id: id.clone(),
+                    status,
+                }
+            })
+            .collect();
+
+        (found, outcomes)
+    }
+
+    pub(crate) async fn redrive(&mut self, ids: Vec<String>) -> Result<(), Error> {
+        for id in &ids {
+            if let Some(dead_letter) = self.dead_letters.remove(id) {
+                let mut message = dead_letter.message;
+                message.retry_count = 0;
+                // A redriven message gets a fresh start, including its time-to-live
+                message.expires_at = None;
+                self.make_ready(message);
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) async fn purge_dead(&mut self) -> Result<(), Error> {
+        self.dead_letters.clear();
+        Ok(())
     }
 }
 
+/// Bounds of the keys greater than `after`, or of all keys without it.
+fn after_bounds<T>(after: Option<T>) -> (Bound<T>, Bound<T>) {
+    (
+        after.map_or(Bound::Unbounded, Bound::Excluded),
+        Bound::Unbounded,
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn test_config() -> QueueConfig {
+        QueueConfig {
+            lock_duration_secs: 60,
+            max_retries: 3,
+            max_message_size: 65536,
+            ttl_secs: 0,
+            dead_letter_expired: false,
+            dedup_window_secs: 300,
+        }
+    }
+
     fn setup_storage() -> BaseMemoryStorage {
         BaseMemoryStorage {
+            config: test_config(),
             queue: vec![
                 Message::new("Hello World".to_string()),
                 Message::new("Hello Solar System".to_string()),
                 Message::new("Hello Universe".to_string()),
-            ],
-            processing: HashMap::new(),
-            dead_count: 0,
+            ]
+            .into_iter()
+            .collect(),
+            processing: BTreeMap::new(),
+            scheduled: BTreeMap::new(),
+            scheduled_ids: BTreeMap::new(),
+            expiring: BTreeMap::new(),
+            dead_letters: BTreeMap::new(),
+            expired: 0,
+            dedup: HashMap::new(),
+            dedup_expiry: BTreeSet::new(),
+            throughput: Throughput::default(),
         }
     }
 
     #[tokio::test]
     async fn test_new_base_memory_storage() {
-        let storage = BaseMemoryStorage::new();
+        let storage = BaseMemoryStorage::new(test_config());
         assert_eq!(storage.queue.len(), 0);
         assert_eq!(storage.processing.len(), 0);
-        assert_eq!(storage.dead_count, 0);
+        assert_eq!(storage.dead_letters.len(), 0);
     }
 
     #[tokio::test]
@@ -155,11 +850,215 @@ mod tests {
         assert_eq!(storage.queue.len(), 5);
     }
 
+    fn scheduled_message(body: &str, deliver_at: i64) -> Message {
+        let mut msg = Message::new(body.to_string());
+        msg.schedule(deliver_at, 0);
+        msg
+    }
+
+    fn dedup_message(body: &str, dedup_id: &str) -> Message {
+        let mut msg = Message::new(body.to_string());
+        msg.dedup_id = Some(dedup_id.to_string());
+        msg
+    }
+
+    #[tokio::test]
+    async fn test_find_duplicate_within_window() {
+        let mut storage = setup_storage();
+        let msg = dedup_message("first", "key");
+        storage.add(msg.clone()).await.unwrap();
+
+        let window_end = msg.created_at() + 300_000;
+        let found = storage.find_duplicate("key", window_end - 1).unwrap();
+        assert_eq!(found.id, msg.id);
+        assert!(storage.find_duplicate("key", window_end).is_none());
+        assert!(storage.find_duplicate("other", 0).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_dedup_disabled_with_zero_window() {
+        let mut storage = BaseMemoryStorage::new(QueueConfig {
+            dedup_window_secs: 0,
+            ..test_config()
+        });
+        storage.add(dedup_message("first", "key")).await.unwrap();
+
+        assert!(storage.find_duplicate("key", 0).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_prune_dedup_forgets_closed_windows() {
+        let mut storage = setup_storage();
+        let msg = dedup_message("first", "key");
+        storage.add(msg.clone()).await.unwrap();
+
+        storage.prune_dedup(msg.created_at() + 300_000);
+        assert!(storage.dedup.is_empty());
+        assert!(storage.dedup_expiry.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_add_scheduled_message_is_not_ready() {
+        let mut storage = setup_storage();
+        storage.add(scheduled_message("later", 1000)).await.unwrap();
+
+        let stats = storage.stats().await.unwrap();
+        assert_eq!(stats.ready, 3);
+        assert_eq!(stats.scheduled, 1);
+        assert_eq!(storage.next_delivery(), Some(1000));
+
+        let messages = storage.get(10, None).await.unwrap();
+        assert!(messages.iter().all(|m| m.body != "later"));
+    }
+
+    #[tokio::test]
+    async fn test_promote_due_in_delivery_order() {
+        let mut storage = BaseMemoryStorage::new(test_config());
+        storage.add(scheduled_message("third", 3000)).await.unwrap();
+        storage.add(scheduled_message("first", 1000)).await.unwrap();
+        storage
+            .add(scheduled_message("second", 2000))
+            .await
+            .unwrap();
+
+        assert_eq!(storage.promote_due(999), 0);
+        assert_eq!(storage.promote_due(2000), 2);
+        let ready: Vec<&Message> = storage.queue.iter().collect();
+        assert_eq!(ready[0].body, "first");
+        assert_eq!(ready[1].body, "second");
+        assert_eq!(ready[0].state, MessageState::Ready);
+        assert_eq!(storage.scheduled.len(), 1);
+        assert_eq!(storage.next_delivery(), Some(3000));
+    }
+
+    fn expiring_message(body: &str, expires_at: i64) -> Message {
+        let mut msg = Message::new(body.to_string());
+        msg.expires_at = Some(expires_at);
+        msg
+    }
+
+    #[tokio::test]
+    async fn test_expire_due_drops_waiting_messages() {
+        let mut storage = setup_storage();
+        storage.add(expiring_message("stale", 1000)).await.unwrap();
+        let mut scheduled = scheduled_message("stale later", 500);
+        scheduled.expires_at = Some(1500);
+        storage.add(scheduled).await.unwrap();
+        storage.add(expiring_message("fresh", 5000)).await.unwrap();
+        assert_eq!(storage.next_expiry(), Some(1000));
+
+        assert_eq!(storage.expire_due(2000), 2);
+        let stats = storage.stats().await.unwrap();
+        assert_eq!(stats.ready, 4);
+        assert_eq!(stats.scheduled, 0);
+        assert_eq!(stats.expired, 2);
+        assert_eq!(stats.dead, 0);
+        assert_eq!(storage.next_expiry(), Some(5000));
+    }
+
+    #[tokio::test]
+    async fn test_expire_due_skips_processing_messages() {
+        let mut storage = BaseMemoryStorage::new(test_config());
+        storage.add(expiring_message("taken", 1000)).await.unwrap();
+        let messages = storage.get(1, None).await.unwrap();
+
+        assert_eq!(storage.expire_due(2000), 0);
+        assert_eq!(storage.processing.len(), 1);
+
+        // A message returned to the queue after its time-to-live expires on the next sweep
+        storage
+            .retry(vec![messages[0].id.to_string()])
+            .await
+            .unwrap();
+        assert_eq!(storage.expire_due(2000), 1);
+        assert_eq!(storage.queue.len(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_expire_due_moves_to_dead_letters() {
+        let mut storage = BaseMemoryStorage::new(QueueConfig {
+            dead_letter_expired: true,
+            ..test_config()
+        });
+        let msg = expiring_message("stale", 1000);
+        storage.add(msg.clone()).await.unwrap();
+
+        assert_eq!(storage.expire_due(1000), 1);
+        let dead_letter = storage
+            .get_dead(&msg.id.to_string())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(dead_letter.reason, DeadReason::Expired);
+        assert_eq!(dead_letter.failed_at, 1000);
+
+        storage.redrive(vec![msg.id.to_string()]).await.unwrap();
+        assert_eq!(storage.expire_due(i64::MAX), 0);
+        assert_eq!(storage.queue.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_redrive_clears_time_to_live() {
+        let mut storage = BaseMemoryStorage::new(test_config());
+        let msg = expiring_message("retried out", i64::MAX - 1);
+        storage.add(msg.clone()).await.unwrap();
+        let delivered = storage.get(1, None).await.unwrap();
+        let id = delivered[0].id.to_string();
+        storage
+            .process_expired(Vec::new(), vec![id.clone()], 0)
+            .await
+            .unwrap();
+
+        // The expiry tracked while the message first waited no longer applies
+        storage.redrive(vec![id]).await.unwrap();
+        assert_eq!(storage.expire_due(i64::MAX), 0);
+        assert_eq!(storage.queue.len(), 1);
+        assert_eq!(storage.expired, 0);
+    }
+
+    #[tokio::test]
+    async fn test_purge_clears_scheduled() {
+        let mut storage = setup_storage();
+        storage.add(scheduled_message("later", 1000)).await.unwrap();
+
+        storage.purge().await.unwrap();
+        assert_eq!(storage.scheduled.len(), 0);
+        assert_eq!(storage.next_delivery(), None);
+    }
+
+    #[tokio::test]
+    async fn test_scheduled_index_follows_messages() {
+        let mut storage = setup_storage();
+        let promoted = scheduled_message("promoted", 1000);
+        let mut expiring = scheduled_message("expiring", 3000);
+        expiring.expires_at = Some(2000);
+        storage.add(promoted.clone()).await.unwrap();
+        storage.add(expiring.clone()).await.unwrap();
+        assert!(storage.contains(&promoted.id));
+        assert_eq!(
+            storage.find(&expiring.id.to_string()).unwrap().body,
+            "expiring"
+        );
+
+        assert_eq!(storage.promote_due(1000), 1);
+        assert_eq!(
+            storage.find(&promoted.id.to_string()).unwrap().state,
+            MessageState::Ready
+        );
+        assert_eq!(storage.expire_due(2000), 1);
+        assert!(!storage.contains(&expiring.id));
+        assert!(storage.scheduled_ids.is_empty());
+
+        storage.add(scheduled_message("later", 5000)).await.unwrap();
+        storage.purge().await.unwrap();
+        assert!(storage.scheduled_ids.is_empty());
+    }
+
     #[tokio::test]
     async fn test_base_memory_storage_get() {
         let mut storage = setup_storage();
 
-        let messages = storage.get(2).await.unwrap();
+        let messages = storage.get(2, None).await.unwrap();
         assert_eq!(messages.len(), 2);
         for message in &messages {
             assert_eq!(message.state, MessageState::Processing);
@@ -168,11 +1067,79 @@ mod tests {
         assert_eq!(storage.processing.len(), 2);
     }
 
+    #[tokio::test]
+    async fn test_get_delivers_higher_priority_first() {
+        let mut storage = setup_storage();
+        let mut urgent = Message::new("urgent".to_string());
+        urgent.priority = 5;
+        storage.add(urgent).await.unwrap();
+
+        let messages = storage.get(2, None).await.unwrap();
+        assert_eq!(messages[0].body, "urgent");
+        assert_eq!(messages[1].body, "Hello World");
+    }
+
+    fn grouped_message(body: &str, group_id: &str) -> Message {
+        let mut message = Message::new(body.to_string());
+        message.group_id = Some(group_id.to_string());
+        message
+    }
+
+    #[tokio::test]
+    async fn test_get_holds_group_while_processing() {
+        let mut storage = setup_storage();
+        storage.purge().await.unwrap();
+        for (body, group_id) in [("a1", "a"), ("a2", "a"), ("b1", "b"), ("b2", "b")] {
+            storage.add(grouped_message(body, group_id)).await.unwrap();
+        }
+        storage.add(Message::new("free".to_string())).await.unwrap();
+
+        let bodies: Vec<String> = storage
+            .get(10, None)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|m| m.body)
+            .collect();
+        assert_eq!(bodies, vec!["a1", "b1", "free"]);
+        assert!(storage.get(10, None).await.unwrap().is_empty());
+
+        let a1 = storage
+            .processing
+            .values()
+            .find(|m| m.body == "a1")
+            .unwrap();
+        storage.delete(vec![a1.id.to_string()]).await.unwrap();
+        let messages = storage.get(10, None).await.unwrap();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].body, "a2");
+    }
+
+    #[tokio::test]
+    async fn test_retry_keeps_grouped_message_first() {
+        let mut storage = setup_storage();
+        storage.purge().await.unwrap();
+        storage.add(grouped_message("a1", "a")).await.unwrap();
+        storage.add(grouped_message("a2", "a")).await.unwrap();
+
+        let messages = storage.get(10, None).await.unwrap();
+        assert_eq!(messages.len(), 1);
+        storage
+            .retry(vec![messages[0].id.to_string()])
+            .await
+            .unwrap();
+
+        let messages = storage.get(10, None).await.unwrap();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].body, "a1");
+        assert_eq!(messages[0].retry_count, 1);
+    }
+
     #[tokio::test]
     async fn test_base_memory_storage_get_more_than_available() {
         let mut storage = setup_storage();
 
-        let messages = storage.get(5).await.unwrap();
+        let messages = storage.get(5, None).await.unwrap();
         assert_eq!(messages.len(), 3);
         assert_eq!(storage.queue.len(), 0);
         assert_eq!(storage.processing.len(), 3);
@@ -182,7 +1149,7 @@ mod tests {
     async fn test_base_memory_storage_delete() {
         let mut storage = setup_storage();
 
-        let messages = storage.get(2).await.unwrap();
+        let messages = storage.get(2, None).await.unwrap();
         storage
             .delete(vec![messages[0].id.to_string(), messages[1].id.to_string()])
             .await
@@ -194,7 +1161,7 @@ mod tests {
     async fn test_base_memory_storage_delete_non_existent() {
         let mut storage = setup_storage();
 
-        let _messages = storage.get(2).await.unwrap();
+        let _messages = storage.get(2, None).await.unwrap();
         storage
             .delete(vec!["non-existent-id".to_string()])
             .await
@@ -206,7 +1173,7 @@ mod tests {
     async fn test_base_memory_storage_delete_duplicate() {
         let mut storage = setup_storage();
 
-        let messages = storage.get(2).await.unwrap();
+        let messages = storage.get(2, None).await.unwrap();
         storage
             .delete(vec![messages[0].id.to_string(), messages[0].id.to_string()])
             .await
@@ -217,7 +1184,7 @@ mod tests {
     #[tokio::test]
     async fn test_base_memory_storage_purge() {
         let mut storage = setup_storage();
-        let _messages = storage.get(1).await.unwrap();
+        let _messages = storage.get(1, None).await.unwrap();
 
         storage.purge().await.unwrap();
         assert_eq!(storage.queue.len(), 0);
@@ -227,7 +1194,7 @@ mod tests {
     #[tokio::test]
     async fn test_base_memory_storage_retry() {
         let mut storage = setup_storage();
-        let messages = storage.get(2).await.unwrap();
+        let messages = storage.get(2, None).await.unwrap();
         storage
             .retry(vec![messages[0].id.to_string()])
             .await
@@ -245,17 +1212,79 @@ mod tests {
         assert_eq!(stats.ready, 3);
         assert_eq!(stats.processing, 0);
 
-        storage.get(2).await.unwrap();
+        storage.get(2, None).await.unwrap();
 
         let stats = storage.stats().await.unwrap();
         assert_eq!(stats.ready, 1);
         assert_eq!(stats.processing, 2);
     }
 
+    #[tokio::test]
+    async fn test_stats_track_age_size_and_throughput() {
+        let mut storage = BaseMemoryStorage::new(test_config());
+        let stats = storage.stats().await.unwrap();
+        assert!(stats.oldest_ready_age_ms.is_none());
+        assert!(stats.oldest_lock_until.is_none());
+
+        let mut urgent = Message::new("urgent".to_string());
+        urgent.priority = 10;
+        let old = Message::new("old".to_string());
+        let mut older = Message::new("older".to_string());
+        older.id = Uuid::new_v7(uuid::Timestamp::from_unix_time(1, 0, 0, 0));
+        for message in [urgent, old, older] {
+            storage.add(message).await.unwrap();
+        }
+
+        let locked = storage.get(2, Some(30)).await.unwrap();
+        let ids: Vec<String> = locked.iter().map(|m| m.id.to_string()).collect();
+        storage.retry(vec![ids[0].clone()]).await.unwrap();
+        storage.delete(vec![ids[1].clone()]).await.unwrap();
+
+        let stats = storage.stats().await.unwrap();
+        assert!(stats.oldest_ready_age_ms.unwrap() >= now_millis() - 1000);
+        assert!(stats.oldest_lock_until.is_none());
+        assert_eq!(stats.bytes, "urgent".len() + "older".len());
+        assert_eq!(stats.enqueued, 3);
+        assert_eq!(stats.acked, 1);
+        assert_eq!(stats.retried, 1);
+        assert_eq!(stats.enqueue_rate, 3.0 / 60.0);
+        assert_eq!(stats.ack_rate, 1.0 / 60.0);
+
+        storage.get(1, None).await.unwrap();
+        let stats = storage.stats().await.unwrap();
+        assert!(stats.oldest_lock_until.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_get_uses_queue_lock_duration() {
+        let mut storage = setup_storage();
+        storage.set_config(QueueConfig {
+            lock_duration_secs: 600,
+            ..test_config()
+        });
+
+        let before = now_millis();
+        let messages = storage.get(1, None).await.unwrap();
+        let lock_until = messages[0].lock_until.unwrap();
+        assert!(lock_until >= before + 600_000);
+        assert!(lock_until <= now_millis() + 600_000);
+    }
+
+    #[tokio::test]
+    async fn test_get_with_lock_secs_overrides_queue_lock_duration() {
+        let mut storage = setup_storage();
+
+        let before = now_millis();
+        let messages = storage.get(1, Some(5)).await.unwrap();
+        let lock_until = messages[0].lock_until.unwrap();
+        assert!(lock_until >= before + 5_000);
+        assert!(lock_until <= now_millis() + 5_000);
+    }
+
     #[tokio::test]
     async fn test_get_sets_lock_until() {
         let mut storage = setup_storage();
-        let messages = storage.get(2).await.unwrap();
+        let messages = storage.get(2, None).await.unwrap();
 
         for msg in &messages {
             assert!(msg.lock_until.is_some());
@@ -265,15 +1294,138 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_extend_pushes_lock_forward() {
+        let mut storage = setup_storage();
+        let messages = storage.get(1, None).await.unwrap();
+        let ids = vec![messages[0].id.to_string()];
+        let lock_until = messages[0].lock_until.unwrap();
+
+        let extended = storage.extend(&ids, 30, 0).await.unwrap();
+        assert_eq!(extended[0].lock_until, Some(lock_until + 30_000));
+        assert_eq!(
+            storage.processing[&ids[0]].lock_until,
+            Some(lock_until + 30_000)
+        );
+
+        // A lock that already lapsed is extended from now
+        let extended = storage.extend(&ids, 10, i64::MAX / 2).await.unwrap();
+        assert_eq!(extended[0].lock_until, Some(i64::MAX / 2 + 10_000));
+
+        // Huge extensions saturate instead of wrapping into the past
+        let extended = storage.extend(&ids, u64::MAX / 1000, 0).await.unwrap();
+        assert_eq!(extended[0].lock_until, Some(i64::MAX));
+    }
+
+    #[tokio::test]
+    async fn test_huge_durations_saturate() {
+        let mut storage = setup_storage();
+        storage.config.lock_duration_secs = u64::MAX;
+        storage.config.dedup_window_secs = u64::MAX;
+
+        let messages = storage.get(1, None).await.unwrap();
+        assert_eq!(messages[0].lock_until, Some(i64::MAX));
+
+        let mut message = Message::new("once".to_string());
+        message.dedup_id = Some("key".to_string());
+        storage.add(message).await.unwrap();
+        assert!(storage.find_duplicate("key", now_millis()).is_some());
+    }
+
+    #[tokio::test]
+    async fn test_get_issues_receipts() {
+        let mut storage = setup_storage();
+        let messages = storage.get(2, None).await.unwrap();
+
+        for message in &messages {
+            let receipt = message.receipt.as_deref().unwrap();
+            assert_eq!(Message::receipt_id(receipt), Some(message.id));
+            assert_eq!(
+                storage.processing[&message.id.to_string()].receipt,
+                message.receipt
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_resolve_receipts() {
+        let mut storage = setup_storage();
+        let messages = storage.get(1, None).await.unwrap();
+        let receipt = messages[0].receipt.clone().unwrap();
+
+        let ids = storage.resolve_receipts(std::slice::from_ref(&receipt), 0);
+        assert_eq!(ids.unwrap(), vec![messages[0].id.to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_resolve_receipts_rejects_stale_and_expired_leases() {
+        let mut storage = setup_storage();
+        let messages = storage.get(1, None).await.unwrap();
+        let id = messages[0].id.to_string();
+        let stale = messages[0].receipt.clone().unwrap();
+        let lock_until = messages[0].lock_until.unwrap();
+
+        // The lease ran out before the reaper reclaimed the message
+        let result = storage.resolve_receipts(std::slice::from_ref(&stale), lock_until);
+        assert_eq!(
+            result.unwrap_err(),
+            Error::Conflict(format!("Receipts expired or no longer valid: [{stale:?}]"))
+        );
+
+        // The message was redelivered under a new receipt
+        storage.retry(vec![id]).await.unwrap();
+        let redelivered = storage.get(3, None).await.unwrap();
+        assert!(redelivered.iter().any(|m| m.id == messages[0].id));
+        assert!(storage
+            .resolve_receipts(std::slice::from_ref(&stale), 0)
+            .is_err());
+        assert!(storage
+            .resolve_receipts(&["malformed".to_string()], 0)
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_settle_receipts_reports_each_outcome() {
+        let mut storage = setup_storage();
+        let messages = storage.get(2, None).await.unwrap();
+        let held = messages[0].receipt.clone().unwrap();
+        let lapsed = messages[1].receipt.clone().unwrap();
+        storage
+            .processing
+            .get_mut(&messages[1].id.to_string())
+            .unwrap()
+            .lock_until = Some(0);
+
+        let waiting = storage.queue.iter().next().unwrap().new_receipt();
+        let unknown = Message::new("gone".to_string()).new_receipt();
+
+        let receipts = vec![held.clone(), lapsed, waiting, unknown, held];
+        let (ids, outcomes) = storage.settle_receipts(&receipts, 1, ReceiptStatus::Deleted);
+        assert_eq!(ids, vec![messages[0].id.to_string()]);
+
+        let statuses: Vec<ReceiptStatus> = outcomes.iter().map(|o| o.status).collect();
+        assert_eq!(
+            statuses,
+            vec![
+                ReceiptStatus::Deleted,
+                ReceiptStatus::LockExpired,
+                ReceiptStatus::NotProcessing,
+                ReceiptStatus::NotFound,
+                ReceiptStatus::Deleted,
+            ]
+        );
+        assert_eq!(outcomes[0].id, Some(messages[0].id));
+    }
+
     #[tokio::test]
     async fn test_retry_clears_lock_until() {
         let mut storage = setup_storage();
-        let messages = storage.get(1).await.unwrap();
+        let messages = storage.get(1, None).await.unwrap();
         let id = messages[0].id.to_string();
 
         storage.retry(vec![id]).await.unwrap();
 
-        let retried = &storage.queue.last().unwrap();
+        let retried = storage.queue.iter().last().unwrap();
         assert!(retried.lock_until.is_none());
         assert_eq!(retried.state, MessageState::Ready);
     }
@@ -288,61 +1440,391 @@ mod tests {
 
     #[tokio::test]
     async fn test_collect_expired_retries_under_max() {
-        let mut storage = BaseMemoryStorage::new();
+        let mut storage = BaseMemoryStorage::new(test_config());
         insert_processing(&mut storage, 0, 0); // expired, under max
 
-        let (to_retry, to_remove) = storage.collect_expired(3);
+        let (to_retry, to_remove) = storage.collect_expired();
         assert_eq!(to_retry.len(), 1);
         assert_eq!(to_remove.len(), 0);
     }
 
     #[tokio::test]
     async fn test_collect_expired_removes_at_max() {
-        let mut storage = BaseMemoryStorage::new();
+        let mut storage = BaseMemoryStorage::new(test_config());
         insert_processing(&mut storage, 0, 3); // expired, at max
 
-        let (to_retry, to_remove) = storage.collect_expired(3);
+        let (to_retry, to_remove) = storage.collect_expired();
         assert_eq!(to_retry.len(), 0);
         assert_eq!(to_remove.len(), 1);
     }
 
     #[tokio::test]
     async fn test_collect_expired_ignores_unexpired() {
-        let mut storage = BaseMemoryStorage::new();
+        let mut storage = BaseMemoryStorage::new(test_config());
         insert_processing(&mut storage, i64::MAX, 0); // not expired
 
-        let (to_retry, to_remove) = storage.collect_expired(3);
+        let (to_retry, to_remove) = storage.collect_expired();
         assert_eq!(to_retry.len(), 0);
         assert_eq!(to_remove.len(), 0);
     }
 
+    #[tokio::test]
+    async fn test_collect_expired_uses_queue_max_retries() {
+        let mut storage = BaseMemoryStorage::new(QueueConfig {
+            max_retries: 5,
+            ..test_config()
+        });
+        insert_processing(&mut storage, 0, 3); // expired, under queue max
+
+        let (to_retry, to_remove) = storage.collect_expired();
+        assert_eq!(to_retry.len(), 1);
+        assert_eq!(to_remove.len(), 0);
+    }
+
     #[tokio::test]
     async fn test_collect_expired_mixed() {
-        let mut storage = BaseMemoryStorage::new();
+        let mut storage = BaseMemoryStorage::new(test_config());
         insert_processing(&mut storage, 0, 0); // expired, retry
         insert_processing(&mut storage, 0, 3); // expired, dead
         insert_processing(&mut storage, i64::MAX, 0); // not expired
 
-        let (to_retry, to_remove) = storage.collect_expired(3);
+        let (to_retry, to_remove) = storage.collect_expired();
         assert_eq!(to_retry.len(), 1);
         assert_eq!(to_remove.len(), 1);
         assert_eq!(storage.processing.len(), 3);
     }
 
     #[tokio::test]
-    async fn test_dead_count_in_stats() {
-        let mut storage = BaseMemoryStorage::new();
-        storage.dead_count = 3;
+    async fn test_claim_moves_ready_messages_to_processing() {
+        let mut storage = setup_storage();
+        let ids = vec![storage.queue.iter().nth(1).unwrap().id.to_string()];
+
+        storage.claim(&ids, 42, &["receipt".to_string()]);
+
+        assert_eq!(storage.queue.len(), 2);
+        assert_eq!(storage.processing.len(), 1);
+        let claimed = &storage.processing[&ids[0]];
+        assert_eq!(claimed.state, MessageState::Processing);
+        assert_eq!(claimed.lock_until, Some(42));
+        assert_eq!(claimed.receipt.as_deref(), Some("receipt"));
+    }
+
+    #[tokio::test]
+    async fn test_process_expired_moves_to_dead_letters() {
+        let mut storage = BaseMemoryStorage::new(test_config());
+        insert_processing(&mut storage, 0, 0); // expired, retry
+        insert_processing(&mut storage, 0, 3); // expired, dead
+
+        let (to_retry, to_remove) = storage.collect_expired();
+        let dead_id = to_remove[0].clone();
+        let result = storage
+            .process_expired(to_retry, to_remove, 1234)
+            .await
+            .unwrap();
+        assert_eq!(result.retried, 1);
+        assert_eq!(result.dead, 1);
+        assert_eq!(storage.queue.len(), 1);
+        assert_eq!(storage.processing.len(), 0);
+
+        let dead_letter = storage.get_dead(&dead_id).await.unwrap().unwrap();
+        assert_eq!(dead_letter.message.state, MessageState::Dead);
+        assert_eq!(dead_letter.message.body, "test");
+        assert_eq!(dead_letter.message.retry_count, 3);
+        assert!(dead_letter.message.lock_until.is_none());
+        assert_eq!(dead_letter.reason, DeadReason::MaxRetriesExceeded);
+        assert_eq!(dead_letter.failed_at, 1234);
+    }
+
+    async fn bury_expired(storage: &mut BaseMemoryStorage, count: usize) -> Vec<String> {
+        for _ in 0..count {
+            insert_processing(storage, 0, 3);
+        }
+        let (_, to_remove) = storage.collect_expired();
+        storage
+            .process_expired(Vec::new(), to_remove.clone(), 0)
+            .await
+            .unwrap();
+        to_remove
+    }
+
+    #[tokio::test]
+    async fn test_dead_letters_in_stats() {
+        let mut storage = BaseMemoryStorage::new(test_config());
+        bury_expired(&mut storage, 3).await;
 
         let stats = storage.stats().await.unwrap();
         assert_eq!(stats.dead, 3);
     }
 
     #[tokio::test]
-    async fn test_purge_resets_dead_count() {
-        let mut storage = BaseMemoryStorage::new();
-        storage.dead_count = 5;
+    async fn test_browse_pages_by_id_without_changing_state() {
+        let mut storage = setup_storage();
+        let locked = storage.get(1, None).await.unwrap();
+        let mut scheduled = Message::new("later".to_string());
+        scheduled.schedule(now_millis() + 60_000, now_millis());
+        storage.add(scheduled).await.unwrap();
+
+        let all = storage.browse(None, None, 10);
+        assert_eq!(all.len(), 4);
+        assert!(all.windows(2).all(|pair| pair[0].id < pair[1].id));
+        assert_eq!(all[0].id, locked[0].id);
+        assert_eq!(all[0].state, MessageState::Processing);
+        assert_eq!(all[0].lock_until, locked[0].lock_until);
+        assert_eq!(all[0].receipt, None);
+        assert_eq!(storage.processing.len(), 1);
+        assert_eq!(storage.queue.len(), 2);
+
+        let page = storage.browse(None, Some(all[1].id), 1);
+        assert_eq!(page.len(), 1);
+        assert_eq!(page[0].id, all[2].id);
+
+        let ready = storage.browse(Some(MessageState::Ready), None, 10);
+        assert_eq!(ready.len(), 2);
+        let scheduled = storage.browse(Some(MessageState::Scheduled), None, 10);
+        assert_eq!(scheduled[0].body, "later");
+    }
+
+    #[tokio::test]
+    async fn test_browse_pages_interleave_states() {
+        let mut storage = BaseMemoryStorage::new(test_config());
+        for index in 0..9 {
+            let mut message = Message::new(format!("message {index}"));
+            if index % 3 == 1 {
+                message.schedule(now_millis() + 60_000, now_millis());
+            }
+            storage.add(message).await.unwrap();
+        }
+        // Lock every other ready message
+        let ready = storage.browse(Some(MessageState::Ready), None, 10);
+        for message in ready.iter().skip(1).step_by(2) {
+            storage.claim(&[message.id.to_string()], i64::MAX, &[]);
+        }
+        assert_eq!(storage.processing.len(), 3);
+
+        let mut paged = Vec::new();
+        let mut after = None;
+        loop {
+            let page = storage.browse(None, after, 2);
+            let Some(last) = page.last() else {
+                break;
+            };
+            assert!(page.len() <= 2);
+            after = Some(last.id);
+            paged.extend(page.into_iter().map(|message| message.body));
+        }
+        let expected: Vec<String> = (0..9).map(|index| format!("message {index}")).collect();
+        assert_eq!(paged, expected);
+
+        let processing = storage.browse(
+            Some(MessageState::Processing),
+            ready.first().map(|m| m.id),
+            1,
+        );
+        assert_eq!(processing[0].id, ready[1].id);
+    }
+
+    #[tokio::test]
+    async fn test_find_looks_everywhere() {
+        let mut storage = setup_storage();
+        let locked = storage.get(1, None).await.unwrap();
+        let ready = storage.browse(Some(MessageState::Ready), None, 1);
+        let mut scheduled = Message::new("later".to_string());
+        scheduled.schedule(now_millis() + 60_000, now_millis());
+        storage.add(scheduled.clone()).await.unwrap();
+        let dead = bury_expired(&mut storage, 1).await;
+
+        let found = storage.find(&locked[0].id.to_string()).unwrap();
+        assert_eq!(found.state, MessageState::Processing);
+        assert_eq!(found.lock_until, locked[0].lock_until);
+        let found = storage.find(&ready[0].id.to_string()).unwrap();
+        assert_eq!(found.state, MessageState::Ready);
+        let found = storage.find(&scheduled.id.to_string()).unwrap();
+        assert_eq!(found.state, MessageState::Scheduled);
+        let found = storage.find(&dead[0]).unwrap();
+        assert_eq!(found.state, MessageState::Dead);
+        assert!(storage.find(&Uuid::now_v7().to_string()).is_none());
+        assert!(storage.find("not-a-uuid").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_import_restores_exported_queue() {
+        let mut storage = setup_storage();
+        let locked = storage.get(1, None).await.unwrap();
+        bury_expired(&mut storage, 1).await;
+        let snapshot = storage.export();
+        assert_eq!(snapshot.messages.len(), 3);
+        assert_eq!(snapshot.dead_letters.len(), 1);
+
+        let mut kept = BaseMemoryStorage::new(snapshot.config);
+        let summary = kept.import(
+            snapshot.messages.clone(),
+            snapshot.dead_letters.clone(),
+            ProcessingRestore::Keep,
+        );
+        assert_eq!(
+            summary,
+            ImportSummary {
+                imported: 3,
+                dead_letters: 1,
+                skipped: 0
+            }
+        );
+        let found = kept.find(&locked[0].id.to_string()).unwrap();
+        assert_eq!(found.state, MessageState::Processing);
+        assert_eq!(found.lock_until, locked[0].lock_until);
+        assert_eq!(found.receipt, locked[0].receipt);
+
+        let mut requeued = BaseMemoryStorage::new(snapshot.config);
+        requeued.import(
+            snapshot.messages,
+            snapshot.dead_letters,
+            ProcessingRestore::Requeue,
+        );
+        let found = requeued.find(&locked[0].id.to_string()).unwrap();
+        assert_eq!(found.state, MessageState::Ready);
+        assert_eq!(found.lock_until, None);
+        assert_eq!(found.receipt, None);
+        assert_eq!(requeued.queue.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_import_skips_existing_ids() {
+        let mut storage = setup_storage();
+        let snapshot = storage.export();
+        let new = Message::new("new".to_string());
+        let mut dead = Message::new("dead".to_string());
+        dead.state = MessageState::Dead;
+
+        let summary = storage.import(
+            snapshot.messages.into_iter().chain([new, dead]).collect(),
+            Vec::new(),
+            ProcessingRestore::Requeue,
+        );
+        assert_eq!(
+            summary,
+            ImportSummary {
+                imported: 1,
+                dead_letters: 0,
+                skipped: 4
+            }
+        );
+        assert_eq!(storage.queue.len(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_restore_rebuilds_checkpointed_queue() {
+        let mut storage = setup_storage();
+        // A deleted message still holds its deduplication window
+        let once = dedup_message("once", "key");
+        storage.add(once.clone()).await.unwrap();
+        storage.claim(&[once.id.to_string()], i64::MAX, &[]);
+        storage.delete(vec![once.id.to_string()]).await.unwrap();
+        bury_expired(&mut storage, 1).await;
+        // A retried message goes behind the others, out of ID order
+        let retried = storage.get(1, None).await.unwrap();
+        storage
+            .retry(vec![retried[0].id.to_string()])
+            .await
+            .unwrap();
+        let locked = storage.get(1, None).await.unwrap();
+        storage
+            .add(scheduled_message("later", now_millis() + 60_000))
+            .await
+            .unwrap();
+
+        let restored = BaseMemoryStorage::restore(storage.checkpoint());
+        let order = |storage: &BaseMemoryStorage| -> Vec<Uuid> {
+            storage.queue.iter().map(|message| message.id).collect()
+        };
+        assert_eq!(order(&restored), order(&storage));
+        assert_eq!(restored.queue.len(), 2);
+        assert_eq!(
+            restored.processing[&locked[0].id.to_string()].receipt,
+            locked[0].receipt
+        );
+        assert_eq!(restored.next_delivery(), storage.next_delivery());
+        assert_eq!(restored.dead_letters.len(), 1);
+        assert_eq!(
+            restored.find_duplicate("key", now_millis()).unwrap().id,
+            once.id
+        );
+
+        let stats = restored.stats().await.unwrap();
+        assert_eq!(stats.processing, 1);
+        assert_eq!(stats.scheduled, 1);
+        assert_eq!(stats.enqueued, 2);
+        assert_eq!(stats.acked, 1);
+        assert_eq!(stats.retried, 1);
+    }
+
+    #[tokio::test]
+    async fn test_list_dead_is_ordered_and_limited() {
+        let mut storage = BaseMemoryStorage::new(test_config());
+        let mut ids = bury_expired(&mut storage, 3).await;
+        ids.sort();
+
+        let listed = storage.list_dead(2).await.unwrap();
+        assert_eq!(listed.len(), 2);
+        assert_eq!(listed[0].message.id.to_string(), ids[0]);
+        assert_eq!(listed[1].message.id.to_string(), ids[1]);
+    }
+
+    #[tokio::test]
+    async fn test_redrive_resets_retry_count() {
+        let mut storage = BaseMemoryStorage::new(test_config());
+        let ids = bury_expired(&mut storage, 2).await;
+
+        storage
+            .redrive(vec![ids[0].clone(), "non-existent-id".to_string()])
+            .await
+            .unwrap();
+
+        assert_eq!(storage.dead_letters.len(), 1);
+        assert_eq!(storage.queue.len(), 1);
+        let redriven = storage.queue.iter().next().unwrap();
+        assert_eq!(redriven.id.to_string(), ids[0]);
+        assert_eq!(redriven.state, MessageState::Ready);
+        assert_eq!(redriven.retry_count, 0);
+    }
+
+    #[tokio::test]
+    async fn test_resolve_dead_letters() {
+        let mut storage = BaseMemoryStorage::new(test_config());
+        let ids = bury_expired(&mut storage, 1).await;
+        let missing = Uuid::now_v7().to_string();
+
+        let (found, outcomes) =
+            storage.resolve_dead_letters(&[ids[0].clone(), missing.clone(), ids[0].clone()]);
+        assert_eq!(found, vec![ids[0].clone()]);
+        let statuses: Vec<(String, RedriveStatus)> = outcomes
+            .into_iter()
+            .map(|outcome| (outcome.id, outcome.status))
+            .collect();
+        assert_eq!(
+            statuses,
+            vec![
+                (ids[0].clone(), RedriveStatus::Redriven),
+                (missing, RedriveStatus::NotFound),
+                (ids[0].clone(), RedriveStatus::NotFound),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_purge_dead_keeps_queue() {
+        let mut storage = setup_storage();
+        bury_expired(&mut storage, 2).await;
+
+        storage.purge_dead().await.unwrap();
+        assert_eq!(storage.dead_letters.len(), 0);
+        assert_eq!(storage.queue.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_purge_clears_dead_letters() {
+        let mut storage = BaseMemoryStorage::new(test_config());
+        bury_expired(&mut storage, 5).await;
         storage.purge().await.unwrap();
-        assert_eq!(storage.dead_count, 0);
+        assert_eq!(storage.dead_letters.len(), 0);
     }
 }
@@ -1,21 +1,172 @@
+use crate::config;
+use crate::errors::Error;
+use crate::storage::notify::QueueNotifier;
 use crate::storage::traits::Storage;
-use crate::types::{Message, QueueStats, ReapResult};
+use crate::storage::{queue_not_found, replace_file};
+use crate::time::now_millis;
+use crate::types::{
+    DeadLetter, ImportSummary, Message, MessageState, ProcessingRestore, QueueConfig,
+    QueueConfigUpdate, QueueInfo, QueueStats, ReapResult, ReceiptOutcome, ReceiptStatus,
+    RedriveOutcome, Snapshot, DEFAULT_QUEUE,
+};
 use async_trait::async_trait;
-use base::BaseMemoryStorage;
+use base::{BaseMemoryStorage, Checkpoint};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use tokio::sync::Mutex;
+use tokio::sync::{Mutex, Notify, RwLock};
+use tracing::info;
+use uuid::Uuid;
 
-mod base;
+pub(crate) mod base;
+mod ready;
+mod throughput;
+
+type Queue = Arc<Mutex<BaseMemoryStorage>>;
 
 pub struct MemoryStorage {
-    inner: Arc<Mutex<BaseMemoryStorage>>,
+    queues: RwLock<HashMap<String, Queue>>,
+    defaults: QueueConfig,
+    notifier: QueueNotifier,
+    /// File written by [`MemoryStorage::save`], if snapshots are enabled
+    snapshot_path: Option<PathBuf>,
+}
+
+/// A line of the snapshot file: the complete state of one queue, as written
+/// to the storage log when it is compacted.
+#[derive(Serialize, Deserialize)]
+struct QueueCheckpoint {
+    queue: String,
+    state: Checkpoint,
+}
+
+/// Reads the queue checkpoints of a snapshot file, one per non-blank line.
+fn decode_checkpoints(input: &str) -> io::Result<Vec<QueueCheckpoint>> {
+    input
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| {
+            serde_json::from_str(line).map_err(|e| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Invalid snapshot on line {}: {e}", index + 1),
+                )
+            })
+        })
+        .collect()
 }
 
 impl MemoryStorage {
     pub fn new() -> Self {
+        let defaults = config::config().queue_defaults();
+        let mut queues = HashMap::new();
+        queues.insert(
+            DEFAULT_QUEUE.to_string(),
+            Arc::new(Mutex::new(BaseMemoryStorage::new(defaults))),
+        );
+
         MemoryStorage {
-            inner: Arc::new(Mutex::new(BaseMemoryStorage::new())),
+            queues: RwLock::new(queues),
+            defaults,
+            notifier: QueueNotifier::new(),
+            snapshot_path: None,
+        }
+    }
+
+    /// Creates a storage that snapshots its queues to `path`, restoring the
+    /// queues from the snapshot already there, if any.
+    ///
+    /// Restored queues keep their delivery order, deduplication windows and
+    /// counters, and their messages keep their state, locks and receipts; locks
+    /// that expired while the server was down are picked up by the reaper.
+    pub async fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        let mut storage = MemoryStorage::new();
+        storage.snapshot_path = Some(path.to_path_buf());
+
+        let file = path.to_path_buf();
+        let read = tokio::task::spawn_blocking(move || match fs::read_to_string(&file) {
+            Ok(input) => decode_checkpoints(&input).map(Some),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        });
+        let Some(queues) = read.await.map_err(io::Error::other)?? else {
+            return Ok(storage);
+        };
+
+        let count = queues.len();
+        let restored = storage.queues.get_mut();
+        for QueueCheckpoint { queue, state } in queues {
+            restored.insert(
+                queue,
+                Arc::new(Mutex::new(BaseMemoryStorage::restore(state))),
+            );
         }
+
+        info!("Restored {} queues from snapshot {}", count, path.display());
+        Ok(storage)
+    }
+
+    /// Writes all queues to the snapshot file, if snapshots are enabled.
+    ///
+    /// The snapshot replaces the previous one atomically, so a crash mid-write
+    /// leaves the previous snapshot intact.
+    pub async fn save(&self) -> Result<(), Error> {
+        let Some(path) = &self.snapshot_path else {
+            return Ok(());
+        };
+
+        let mut queues: Vec<(String, Queue)> = self
+            .queues
+            .read()
+            .await
+            .iter()
+            .map(|(name, queue)| (name.clone(), queue.clone()))
+            .collect();
+        queues.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let mut output = Vec::new();
+        for (queue, storage) in queues {
+            let state = storage.lock().await.checkpoint();
+            serde_json::to_writer(&mut output, &QueueCheckpoint { queue, state })
+                .map_err(|e| Error::Storage(format!("Failed to encode snapshot: {e}")))?;
+            output.push(b'\n');
+        }
+        let path = path.clone();
+        let write = tokio::task::spawn_blocking(move || {
+            replace_file(&path, &output)
+                .map(|_| ())
+                .map_err(|e| Error::Storage(format!("Failed to write snapshot: {e}")))
+        });
+        write
+            .await
+            .map_err(|e| Error::Storage(format!("Failed to write snapshot: {e}")))?
+    }
+
+    async fn queue(&self, name: &str) -> Result<Queue, Error> {
+        self.queues
+            .read()
+            .await
+            .get(name)
+            .cloned()
+            .ok_or_else(|| queue_not_found(name))
+    }
+
+    async fn queue_or_create(&self, name: &str) -> Queue {
+        if let Some(queue) = self.queues.read().await.get(name) {
+            return queue.clone();
+        }
+
+        self.queues
+            .write()
+            .await
+            .entry(name.to_string())
+            .or_insert_with(|| Arc::new(Mutex::new(BaseMemoryStorage::new(self.defaults))))
+            .clone()
     }
 }
 
@@ -27,50 +178,543 @@ impl Default for MemoryStorage {
 
 #[async_trait]
 impl Storage for MemoryStorage {
-    async fn add(&self, msg: Message) -> Result<(), String> {
-        let mut storage = self.inner.lock().await;
-        storage.add(msg).await
+    async fn create_queue(&self, queue: &str, config: QueueConfig) -> Result<(), Error> {
+        let mut queues = self.queues.write().await;
+        if queues.contains_key(queue) {
+            return Err(Error::Conflict(format!("Queue '{queue}' already exists")));
+        }
+
+        queues.insert(
+            queue.to_string(),
+            Arc::new(Mutex::new(BaseMemoryStorage::new(config))),
+        );
+        Ok(())
+    }
+
+    async fn delete_queue(&self, queue: &str) -> Result<(), Error> {
+        self.queues
+            .write()
+            .await
+            .remove(queue)
+            .ok_or_else(|| queue_not_found(queue))?;
+        self.notifier.remove(queue);
+        Ok(())
+    }
+
+    async fn list_queues(&self) -> Result<Vec<QueueInfo>, Error> {
+        let queues: Vec<(String, Queue)> = self
+            .queues
+            .read()
+            .await
+            .iter()
+            .map(|(name, queue)| (name.clone(), queue.clone()))
+            .collect();
+
+        let mut infos = Vec::with_capacity(queues.len());
+        for (name, queue) in queues {
+            let storage = queue.lock().await;
+            infos.push(QueueInfo {
+                name,
+                stats: storage.stats().await?,
+                config: storage.config(),
+            });
+        }
+        infos.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Ok(infos)
+    }
+
+    async fn queue_config(&self, queue: &str) -> Result<Option<QueueConfig>, Error> {
+        match self.queues.read().await.get(queue) {
+            Some(queue) => Ok(Some(queue.lock().await.config())),
+            None => Ok(None),
+        }
+    }
+
+    async fn update_queue_config(
+        &self,
+        queue: &str,
+        update: QueueConfigUpdate,
+    ) -> Result<QueueConfig, Error> {
+        let queue = self.queue(queue).await?;
+        let mut storage = queue.lock().await;
+        let config = storage.config().merge(&update);
+        storage.set_config(config);
+        Ok(config)
+    }
+
+    async fn add(&self, queue: &str, msg: Message) -> Result<Message, Error> {
+        let mut added = self.add_batch(queue, vec![msg]).await?;
+        Ok(added.remove(0))
+    }
+
+    async fn add_batch(&self, queue: &str, msgs: Vec<Message>) -> Result<Vec<Message>, Error> {
+        let storage = self.queue_or_create(queue).await;
+        let mut added = Vec::with_capacity(msgs.len());
+        let mut enqueued = false;
+        {
+            let mut storage = storage.lock().await;
+            let now = now_millis();
+            for msg in msgs {
+                if let Some(original) = msg
+                    .dedup_id
+                    .as_deref()
+                    .and_then(|dedup_id| storage.find_duplicate(dedup_id, now))
+                {
+                    added.push(original.clone());
+                    continue;
+                }
+                storage.add(msg.clone()).await?;
+                added.push(msg);
+                enqueued = true;
+            }
+        }
+        if enqueued {
+            self.notifier.notify(queue);
+        }
+        Ok(added)
     }
 
-    async fn get(&self, count: usize) -> Result<Vec<Message>, String> {
-        let mut storage = self.inner.lock().await;
-        storage.get(count).await
+    async fn get(
+        &self,
+        queue: &str,
+        count: usize,
+        lock_secs: Option<u64>,
+    ) -> Result<Vec<Message>, Error> {
+        let queue = self.queue(queue).await?;
+        let mut storage = queue.lock().await;
+        let now = now_millis();
+        storage.expire_due(now);
+        storage.promote_due(now);
+        storage.get(count, lock_secs).await
     }
 
-    async fn stats(&self) -> Result<QueueStats, String> {
-        let storage = self.inner.lock().await;
+    async fn stats(&self, queue: &str) -> Result<QueueStats, Error> {
+        let queue = self.queue(queue).await?;
+        let storage = queue.lock().await;
         storage.stats().await
     }
 
-    async fn delete(&self, ids: Vec<String>) -> Result<(), String> {
-        let mut storage = self.inner.lock().await;
-        storage.delete(ids).await
+    async fn delete(
+        &self,
+        queue: &str,
+        receipts: Vec<String>,
+    ) -> Result<Vec<ReceiptOutcome>, Error> {
+        let storage = self.queue(queue).await?;
+        let mut storage = storage.lock().await;
+        let (ids, outcomes) =
+            storage.settle_receipts(&receipts, now_millis(), ReceiptStatus::Deleted);
+        if ids.is_empty() {
+            return Ok(outcomes);
+        }
+
+        storage.delete(ids).await?;
+        // Deleting the message a group waited on makes the group's next message ready
+        self.notifier.notify(queue);
+        Ok(outcomes)
     }
 
-    async fn purge(&self) -> Result<(), String> {
-        let mut storage = self.inner.lock().await;
+    async fn purge(&self, queue: &str) -> Result<(), Error> {
+        let queue = self.queue(queue).await?;
+        let mut storage = queue.lock().await;
         storage.purge().await
     }
 
-    async fn retry(&self, ids: Vec<String>) -> Result<(), String> {
-        let mut storage = self.inner.lock().await;
-        storage.retry(ids).await
+    async fn retry(
+        &self,
+        queue: &str,
+        receipts: Vec<String>,
+    ) -> Result<Vec<ReceiptOutcome>, Error> {
+        let storage = self.queue(queue).await?;
+        let (ids, outcomes) = {
+            let mut storage = storage.lock().await;
+            let (ids, outcomes) =
+                storage.settle_receipts(&receipts, now_millis(), ReceiptStatus::Retried);
+            storage.retry(ids.clone()).await?;
+            (ids, outcomes)
+        };
+        if !ids.is_empty() {
+            self.notifier.notify(queue);
+        }
+        Ok(outcomes)
     }
 
-    async fn reap_expired(&self, max_retries: u32) -> Result<ReapResult, String> {
-        let (to_retry, to_remove) = {
-            let storage = self.inner.lock().await;
-            storage.collect_expired(max_retries)
-        };
+    async fn extend(
+        &self,
+        queue: &str,
+        receipts: Vec<String>,
+        extend_secs: u64,
+    ) -> Result<Vec<Message>, Error> {
+        let queue = self.queue(queue).await?;
+        let mut storage = queue.lock().await;
+        let now = now_millis();
+        let ids = storage.resolve_receipts(&receipts, now)?;
+        storage.extend(&ids, extend_secs, now).await
+    }
 
-        if to_retry.is_empty() && to_remove.is_empty() {
-            return Ok(ReapResult {
-                retried: 0,
-                dead: 0,
-            });
+    async fn reap_expired(&self) -> Result<ReapResult, Error> {
+        let queues: Vec<(String, Queue)> = self
+            .queues
+            .read()
+            .await
+            .iter()
+            .map(|(name, queue)| (name.clone(), queue.clone()))
+            .collect();
+        let mut total = ReapResult::default();
+
+        for (name, queue) in queues {
+            let (promoted, to_retry, to_remove) = {
+                let mut storage = queue.lock().await;
+                let now = now_millis();
+                total.expired += storage.expire_due(now);
+                let promoted = storage.promote_due(now);
+                let (to_retry, to_remove) = storage.collect_expired();
+                (promoted, to_retry, to_remove)
+            };
+            total.promoted += promoted;
+
+            let mut retried = 0;
+            if !to_retry.is_empty() || !to_remove.is_empty() {
+                let mut storage = queue.lock().await;
+                let result = storage
+                    .process_expired(to_retry, to_remove, now_millis())
+                    .await?;
+                retried = result.retried;
+                total.retried += result.retried;
+                total.dead += result.dead;
+            }
+
+            if promoted > 0 || retried > 0 {
+                self.notifier.notify(&name);
+            }
         }
 
-        let mut storage = self.inner.lock().await;
-        storage.process_expired(to_retry, to_remove).await
+        Ok(total)
+    }
+
+    async fn browse(
+        &self,
+        queue: &str,
+        state: Option<MessageState>,
+        after: Option<Uuid>,
+        limit: usize,
+    ) -> Result<Vec<Message>, Error> {
+        let queue = self.queue(queue).await?;
+        let storage = queue.lock().await;
+        Ok(storage.browse(state, after, limit))
+    }
+
+    async fn find(&self, queue: &str, id: String) -> Result<Option<Message>, Error> {
+        let queue = self.queue(queue).await?;
+        let storage = queue.lock().await;
+        Ok(storage.find(&id).map(Message::without_receipt))
+    }
+
+    async fn list_dead(&self, queue: &str, limit: usize) -> Result<Vec<DeadLetter>, Error> {
+        let queue = self.queue(queue).await?;
+        let storage = queue.lock().await;
+        storage.list_dead(limit).await
+    }
+
+    async fn get_dead(&self, queue: &str, id: String) -> Result<Option<DeadLetter>, Error> {
+        let queue = self.queue(queue).await?;
+        let storage = queue.lock().await;
+        storage.get_dead(&id).await
+    }
+
+    async fn redrive(&self, queue: &str, ids: Vec<String>) -> Result<Vec<RedriveOutcome>, Error> {
+        let storage = self.queue(queue).await?;
+        let mut storage = storage.lock().await;
+        let (ids, outcomes) = storage.resolve_dead_letters(&ids);
+        if ids.is_empty() {
+            return Ok(outcomes);
+        }
+
+        storage.redrive(ids).await?;
+        self.notifier.notify(queue);
+        Ok(outcomes)
+    }
+
+    async fn purge_dead(&self, queue: &str) -> Result<(), Error> {
+        let queue = self.queue(queue).await?;
+        let mut storage = queue.lock().await;
+        storage.purge_dead().await
+    }
+
+    async fn export(&self, queue: &str) -> Result<Snapshot, Error> {
+        let queue = self.queue(queue).await?;
+        let storage = queue.lock().await;
+        Ok(storage.export())
+    }
+
+    async fn import(
+        &self,
+        queue: &str,
+        snapshot: Snapshot,
+        processing: ProcessingRestore,
+    ) -> Result<ImportSummary, Error> {
+        let storage = {
+            let mut queues = self.queues.write().await;
+            queues
+                .entry(queue.to_string())
+                .or_insert_with(|| Arc::new(Mutex::new(BaseMemoryStorage::new(snapshot.config))))
+                .clone()
+        };
+        let summary =
+            storage
+                .lock()
+                .await
+                .import(snapshot.messages, snapshot.dead_letters, processing);
+        self.notifier.notify(queue);
+        Ok(summary)
+    }
+
+    async fn flush(&self) -> Result<(), Error> {
+        self.save().await
+    }
+
+    fn subscribe(&self, queue: &str) -> Arc<Notify> {
+        self.notifier.subscribe(queue)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_default_queue_exists() {
+        let storage = MemoryStorage::new();
+        let queues = storage.list_queues().await.unwrap();
+        assert_eq!(queues.len(), 1);
+        assert_eq!(queues[0].name, DEFAULT_QUEUE);
+    }
+
+    #[tokio::test]
+    async fn test_add_creates_queue_implicitly() {
+        let storage = MemoryStorage::new();
+        storage
+            .add("orders", Message::new("order".to_string()))
+            .await
+            .unwrap();
+
+        assert_eq!(storage.stats("orders").await.unwrap().ready, 1);
+        assert_eq!(storage.stats(DEFAULT_QUEUE).await.unwrap().ready, 0);
+    }
+
+    #[tokio::test]
+    async fn test_queues_are_independent() {
+        let storage = MemoryStorage::new();
+        storage
+            .create_queue("emails", storage.defaults)
+            .await
+            .unwrap();
+        storage
+            .add("emails", Message::new("email".to_string()))
+            .await
+            .unwrap();
+        storage
+            .add(DEFAULT_QUEUE, Message::new("default".to_string()))
+            .await
+            .unwrap();
+
+        let messages = storage.get("emails", 10, None).await.unwrap();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].body, "email");
+
+        storage.purge(DEFAULT_QUEUE).await.unwrap();
+        assert_eq!(storage.stats("emails").await.unwrap().processing, 1);
+    }
+
+    #[tokio::test]
+    async fn test_reaper_promotes_due_messages() {
+        let storage = MemoryStorage::new();
+        let mut due = Message::new("due".to_string());
+        due.schedule(1, 0);
+        storage.add(DEFAULT_QUEUE, due).await.unwrap();
+
+        let stats = storage.stats(DEFAULT_QUEUE).await.unwrap();
+        assert_eq!(stats.scheduled, 1);
+
+        let result = storage.reap_expired().await.unwrap();
+        assert_eq!(result.promoted, 1);
+        let stats = storage.stats(DEFAULT_QUEUE).await.unwrap();
+        assert_eq!(stats.ready, 1);
+        assert_eq!(stats.scheduled, 0);
+    }
+
+    #[tokio::test]
+    async fn test_reaper_expires_messages() {
+        let storage = MemoryStorage::new();
+        let mut stale = Message::new("stale".to_string());
+        stale.expires_at = Some(1);
+        storage.add(DEFAULT_QUEUE, stale).await.unwrap();
+
+        let result = storage.reap_expired().await.unwrap();
+        assert_eq!(result.expired, 1);
+        let stats = storage.stats(DEFAULT_QUEUE).await.unwrap();
+        assert_eq!(stats.ready, 0);
+        assert_eq!(stats.expired, 1);
+    }
+
+    #[tokio::test]
+    async fn test_update_queue_config() {
+        let storage = MemoryStorage::new();
+        let config = QueueConfig {
+            lock_duration_secs: 30,
+            max_retries: 1,
+            max_message_size: 128,
+            ttl_secs: 0,
+            dead_letter_expired: false,
+            dedup_window_secs: 300,
+        };
+        storage.create_queue("jobs", config).await.unwrap();
+        assert_eq!(storage.queue_config("jobs").await.unwrap(), Some(config));
+
+        let updated = storage
+            .update_queue_config(
+                "jobs",
+                QueueConfigUpdate {
+                    max_retries: Some(10),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(updated.max_retries, 10);
+        assert_eq!(updated.lock_duration_secs, 30);
+        assert_eq!(storage.queue_config("jobs").await.unwrap(), Some(updated));
+        assert_eq!(storage.queue_config("missing").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_create_existing_queue_fails() {
+        let storage = MemoryStorage::new();
+        let result = storage.create_queue(DEFAULT_QUEUE, storage.defaults).await;
+        assert_eq!(
+            result.unwrap_err(),
+            Error::Conflict("Queue 'default' already exists".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unknown_queue_fails() {
+        let storage = MemoryStorage::new();
+        assert_eq!(
+            storage.get("missing", 1, None).await.unwrap_err(),
+            queue_not_found("missing")
+        );
+        assert_eq!(
+            storage.delete_queue("missing").await.unwrap_err(),
+            queue_not_found("missing")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_delete_queue() {
+        let storage = MemoryStorage::new();
+        storage
+            .create_queue("temp", storage.defaults)
+            .await
+            .unwrap();
+        storage.delete_queue("temp").await.unwrap();
+
+        assert!(storage.stats("temp").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_restores_queues() {
+        let path = std::env::temp_dir().join(format!("tlq-{}.jsonl", Uuid::now_v7()));
+        let (first, locked) = {
+            let storage = MemoryStorage::open(&path).await.unwrap();
+            let mut config = storage.defaults;
+            config.max_retries = 7;
+            storage.create_queue("orders", config).await.unwrap();
+            let mut first = Message::new("one".to_string());
+            first.dedup_id = Some("first".to_string());
+            let first = storage.add("orders", first).await.unwrap();
+            for body in ["two", "three"] {
+                storage
+                    .add("orders", Message::new(body.to_string()))
+                    .await
+                    .unwrap();
+            }
+            let mut expiring = Message::new("stale".to_string());
+            expiring.expires_at = Some(1);
+            storage.add("orders", expiring).await.unwrap();
+            assert_eq!(storage.reap_expired().await.unwrap().expired, 1);
+
+            // A retried message goes behind the ones added after it
+            let retried = storage.get("orders", 1, None).await.unwrap();
+            storage
+                .retry("orders", vec![retried[0].receipt.clone().unwrap()])
+                .await
+                .unwrap();
+            let acked = storage.get("orders", 1, None).await.unwrap();
+            assert_eq!(acked[0].body, "two");
+            storage
+                .delete("orders", vec![acked[0].receipt.clone().unwrap()])
+                .await
+                .unwrap();
+            let locked = storage.get("orders", 1, None).await.unwrap();
+            assert_eq!(locked[0].body, "three");
+            storage
+                .add("orders", Message::new("four".to_string()))
+                .await
+                .unwrap();
+            storage.flush().await.unwrap();
+            (first, locked)
+        };
+
+        let storage = MemoryStorage::open(&path).await.unwrap();
+        assert_eq!(
+            storage
+                .queue_config("orders")
+                .await
+                .unwrap()
+                .unwrap()
+                .max_retries,
+            7
+        );
+        let stats = storage.stats("orders").await.unwrap();
+        assert_eq!(stats.ready, 2);
+        assert_eq!(stats.processing, 1);
+        assert_eq!(stats.expired, 1);
+        assert_eq!(stats.enqueued, 5);
+        assert_eq!(stats.acked, 1);
+        assert_eq!(stats.retried, 1);
+
+        let mut duplicate = Message::new("again".to_string());
+        duplicate.dedup_id = Some("first".to_string());
+        assert_eq!(storage.add("orders", duplicate).await.unwrap().id, first.id);
+
+        let outcomes = storage
+            .delete("orders", vec![locked[0].receipt.clone().unwrap()])
+            .await
+            .unwrap();
+        assert_eq!(outcomes[0].status, ReceiptStatus::Deleted);
+
+        let delivered = storage.get("orders", 2, None).await.unwrap();
+        let bodies: Vec<&str> = delivered.iter().map(|m| m.body.as_str()).collect();
+        assert_eq!(bodies, vec!["one", "four"]);
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn test_save_without_snapshot_path_is_noop() {
+        let storage = MemoryStorage::new();
+        storage.save().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_corrupted_snapshot_fails_to_open() {
+        let path = std::env::temp_dir().join(format!("tlq-{}.jsonl", Uuid::now_v7()));
+        std::fs::write(&path, "not json\n").unwrap();
+
+        let err = MemoryStorage::open(&path).await.err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        let _ = std::fs::remove_file(&path);
     }
 }
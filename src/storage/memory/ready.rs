@@ -0,0 +1,343 @@
+use crate::types::Message;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::ops::Bound;
+use uuid::Uuid;
+
+/// Orders ready messages by descending priority, then by arrival
+type ReadyKey = (Reverse<i32>, i64);
+
+/// Ready messages in delivery order: higher priorities first, FIFO within a priority.
+///
+/// Messages sharing a `group_id` are delivered one at a time: only the first
+/// ready message of a group is deliverable, and none while the group is locked.
+#[derive(Default)]
+pub(crate) struct ReadyQueue {
+    messages: BTreeMap<ReadyKey, Message>,
+    /// Keys by message ID; UUID v7 IDs make the first entry the oldest message
+    keys: BTreeMap<Uuid, ReadyKey>,
+    /// Keys of the messages that can be delivered next: every ungrouped message
+    /// and the first message of each group that is not locked
+    deliverable: BTreeSet<ReadyKey>,
+    /// Keys of the ready messages of each group
+    groups: HashMap<String, BTreeSet<ReadyKey>>,
+    /// Number of processing messages per locked group
+    locked: HashMap<String, usize>,
+    next_seq: i64,
+    /// Decreasing sequence for messages put back ahead of their priority
+    front_seq: i64,
+}
+
+impl ReadyQueue {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.messages.len()
+    }
+
+    /// Appends a message behind all ready messages of the same priority.
+    pub(crate) fn push(&mut self, message: Message) {
+        let key = (Reverse(message.priority), self.next_seq);
+        self.next_seq += 1;
+        self.insert(key, message);
+    }
+
+    /// Inserts a message ahead of all ready messages of the same priority.
+    pub(crate) fn push_front(&mut self, message: Message) {
+        self.front_seq -= 1;
+        let key = (Reverse(message.priority), self.front_seq);
+        self.insert(key, message);
+    }
+
+    fn insert(&mut self, key: ReadyKey, message: Message) {
+        self.keys.insert(message.id, key);
+        match &message.group_id {
+            Some(group) => {
+                let head = self.head(group);
+                self.groups.entry(group.clone()).or_default().insert(key);
+                self.move_head(group, head);
+            }
+            None => {
+                self.deliverable.insert(key);
+            }
+        }
+        self.messages.insert(key, message);
+    }
+
+    pub(crate) fn contains(&self, id: &Uuid) -> bool {
+        self.keys.contains_key(id)
+    }
+
+    /// The message that was created first, whatever its priority.
+    pub(crate) fn oldest(&self) -> Option<&Message> {
+        self.messages.get(self.keys.values().next()?)
+    }
+
+    pub(crate) fn get(&self, id: &Uuid) -> Option<&Message> {
+        self.messages.get(self.keys.get(id)?)
+    }
+
+    /// The messages that would be delivered next, in delivery order. Takes at
+    /// most one message per group, as its other messages wait for it to finish.
+    pub(crate) fn deliverable(&self) -> impl Iterator<Item = &Message> {
+        self.deliverable.iter().map(|key| &self.messages[key])
+    }
+
+    /// Removes and returns the next message to deliver.
+    #[cfg(test)]
+    pub(crate) fn pop(&mut self) -> Option<Message> {
+        let key = *self.deliverable.first()?;
+        self.remove_key(key)
+    }
+
+    /// Removes the message with the given ID, wherever it is in the queue.
+    pub(crate) fn remove(&mut self, id: &Uuid) -> Option<Message> {
+        let key = *self.keys.get(id)?;
+        self.remove_key(key)
+    }
+
+    fn remove_key(&mut self, key: ReadyKey) -> Option<Message> {
+        let message = self.messages.remove(&key)?;
+        self.keys.remove(&message.id);
+        match &message.group_id {
+            Some(group) => {
+                let head = self.head(group);
+                if let Some(keys) = self.groups.get_mut(group) {
+                    keys.remove(&key);
+                    if keys.is_empty() {
+                        self.groups.remove(group);
+                    }
+                }
+                self.move_head(group, head);
+            }
+            None => {
+                self.deliverable.remove(&key);
+            }
+        }
+        Some(message)
+    }
+
+    /// Holds back the messages of `group` while one of its messages is processing.
+    pub(crate) fn lock_group(&mut self, group: &str) {
+        let head = self.head(group);
+        *self.locked.entry(group.to_string()).or_default() += 1;
+        self.move_head(group, head);
+    }
+
+    /// Releases `group` once none of its messages are processing anymore.
+    pub(crate) fn unlock_group(&mut self, group: &str) {
+        let head = self.head(group);
+        if let Some(count) = self.locked.get_mut(group) {
+            *count -= 1;
+            if *count == 0 {
+                self.locked.remove(group);
+            }
+        }
+        self.move_head(group, head);
+    }
+
+    /// The deliverable message of `group`, if the group has one.
+    fn head(&self, group: &str) -> Option<ReadyKey> {
+        if self.locked.contains_key(group) {
+            return None;
+        }
+        self.groups.get(group)?.first().copied()
+    }
+
+    /// Makes the current head of `group` deliverable in place of `previous`.
+    fn move_head(&mut self, group: &str, previous: Option<ReadyKey>) {
+        let head = self.head(group);
+        if head == previous {
+            return;
+        }
+        if let Some(key) = previous {
+            self.deliverable.remove(&key);
+        }
+        if let Some(key) = head {
+            self.deliverable.insert(key);
+        }
+    }
+
+    /// Iterates over the messages with an ID greater than `after`, or over all
+    /// of them without it, in ID order.
+    pub(crate) fn after(&self, after: Option<Uuid>) -> impl Iterator<Item = &Message> {
+        let from = after.map_or(Bound::Unbounded, Bound::Excluded);
+        self.keys
+            .range((from, Bound::Unbounded))
+            .map(|(_, key)| &self.messages[key])
+    }
+
+    /// Iterates over the messages in delivery order.
+    pub(crate) fn iter(&self) -> impl DoubleEndedIterator<Item = &Message> {
+        self.messages.values()
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.messages.clear();
+        self.keys.clear();
+        self.deliverable.clear();
+        self.groups.clear();
+        self.locked.clear();
+    }
+}
+
+impl Extend<Message> for ReadyQueue {
+    fn extend<I: IntoIterator<Item = Message>>(&mut self, messages: I) {
+        for message in messages {
+            self.push(message);
+        }
+    }
+}
+
+impl FromIterator<Message> for ReadyQueue {
+    fn from_iter<I: IntoIterator<Item = Message>>(messages: I) -> Self {
+        let mut queue = ReadyQueue::new();
+        queue.extend(messages);
+        queue
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(body: &str, priority: i32) -> Message {
+        let mut msg = Message::new(body.to_string());
+        msg.priority = priority;
+        msg
+    }
+
+    #[test]
+    fn test_pop_orders_by_priority_then_fifo() {
+        let mut queue: ReadyQueue = vec![
+            message("low", -1),
+            message("first", 0),
+            message("urgent", 10),
+            message("second", 0),
+        ]
+        .into_iter()
+        .collect();
+
+        let bodies: Vec<String> = std::iter::from_fn(|| queue.pop()).map(|m| m.body).collect();
+        assert_eq!(bodies, vec!["urgent", "first", "second", "low"]);
+        assert_eq!(queue.len(), 0);
+    }
+
+    #[test]
+    fn test_remove_by_id() {
+        let first = message("first", 0);
+        let second = message("second", 0);
+        let mut queue: ReadyQueue = vec![first.clone(), second.clone()].into_iter().collect();
+
+        assert_eq!(queue.remove(&second.id).unwrap().body, "second");
+        assert!(queue.remove(&second.id).is_none());
+        assert_eq!(queue.len(), 1);
+        assert_eq!(queue.pop().unwrap().id, first.id);
+    }
+
+    #[test]
+    fn test_after_iterates_in_id_order() {
+        let first = message("first", 0);
+        let second = message("second", 10);
+        let third = message("third", 5);
+        let queue: ReadyQueue = vec![first.clone(), second.clone(), third.clone()]
+            .into_iter()
+            .collect();
+
+        let all: Vec<&str> = queue.after(None).map(|m| m.body.as_str()).collect();
+        assert_eq!(all, vec!["first", "second", "third"]);
+        let rest: Vec<&str> = queue
+            .after(Some(first.id))
+            .map(|m| m.body.as_str())
+            .collect();
+        assert_eq!(rest, vec!["second", "third"]);
+        assert_eq!(queue.after(Some(third.id)).count(), 0);
+    }
+
+    #[test]
+    fn test_oldest_ignores_priority() {
+        let first = message("first", 0);
+        let urgent = message("urgent", 10);
+        let mut queue: ReadyQueue = vec![first.clone(), urgent].into_iter().collect();
+
+        assert_eq!(queue.oldest().unwrap().id, first.id);
+        queue.remove(&first.id);
+        assert_eq!(queue.oldest().unwrap().body, "urgent");
+        queue.clear();
+        assert!(queue.oldest().is_none());
+    }
+
+    #[test]
+    fn test_push_front_goes_ahead_of_priority() {
+        let mut queue: ReadyQueue = vec![message("urgent", 10), message("a", 0), message("b", 0)]
+            .into_iter()
+            .collect();
+
+        queue.push_front(message("retried", 0));
+        let bodies: Vec<&str> = queue.iter().map(|m| m.body.as_str()).collect();
+        assert_eq!(bodies, vec!["urgent", "retried", "a", "b"]);
+    }
+
+    fn grouped(body: &str, group: &str) -> Message {
+        let mut msg = message(body, 0);
+        msg.group_id = Some(group.to_string());
+        msg
+    }
+
+    #[test]
+    fn test_one_deliverable_message_per_group() {
+        let mut queue: ReadyQueue = vec![
+            grouped("a1", "a"),
+            grouped("a2", "a"),
+            message("solo", 0),
+            grouped("b1", "b"),
+        ]
+        .into_iter()
+        .collect();
+
+        let bodies: Vec<&str> = queue.deliverable().map(|m| m.body.as_str()).collect();
+        assert_eq!(bodies, vec!["a1", "solo", "b1"]);
+
+        queue.lock_group("a");
+        let bodies: Vec<&str> = queue.deliverable().map(|m| m.body.as_str()).collect();
+        assert_eq!(bodies, vec!["solo", "b1"]);
+
+        let a1 = queue.iter().next().unwrap().id;
+        queue.remove(&a1);
+        queue.unlock_group("a");
+        let bodies: Vec<&str> = queue.deliverable().map(|m| m.body.as_str()).collect();
+        assert_eq!(bodies, vec!["a2", "solo", "b1"]);
+        assert_eq!(queue.len(), 3);
+    }
+
+    #[test]
+    fn test_group_stays_locked_until_every_lock_is_released() {
+        let mut queue: ReadyQueue = vec![grouped("a1", "a")].into_iter().collect();
+
+        queue.lock_group("a");
+        queue.lock_group("a");
+        queue.unlock_group("a");
+        assert_eq!(queue.deliverable().count(), 0);
+        queue.unlock_group("a");
+        assert_eq!(queue.deliverable().count(), 1);
+
+        queue.push_front(grouped("retried", "a"));
+        let bodies: Vec<&str> = queue.deliverable().map(|m| m.body.as_str()).collect();
+        assert_eq!(bodies, vec!["retried"]);
+        assert_eq!(queue.pop().unwrap().body, "retried");
+        assert_eq!(queue.pop().unwrap().body, "a1");
+        assert!(queue.pop().is_none());
+    }
+
+    #[test]
+    fn test_push_after_pop_goes_to_back_of_priority() {
+        let mut queue: ReadyQueue = vec![message("a", 0), message("b", 0)].into_iter().collect();
+
+        let a = queue.pop().unwrap();
+        queue.push(a);
+        let bodies: Vec<&str> = queue.iter().map(|m| m.body.as_str()).collect();
+        assert_eq!(bodies, vec!["b", "a"]);
+    }
+}
@@ -0,0 +1,120 @@
+use std::collections::VecDeque;
+
+/// Length of the window over which rates are averaged
+const RATE_WINDOW_SECS: i64 = 60;
+
+/// Counts events per second over a sliding window of [`RATE_WINDOW_SECS`].
+#[derive(Default)]
+pub(crate) struct RateWindow {
+    /// Event counts per second, oldest first
+    buckets: VecDeque<(i64, u64)>,
+}
+
+impl RateWindow {
+    pub(crate) fn record(&mut self, now: i64, count: u64) {
+        if count == 0 {
+            return;
+        }
+
+        let second = now.div_euclid(1000);
+        match self.buckets.back_mut() {
+            Some((last, total)) if *last == second => *total += count,
+            _ => self.buckets.push_back((second, count)),
+        }
+        while self
+            .buckets
+            .front()
+            .is_some_and(|(first, _)| *first <= second - RATE_WINDOW_SECS)
+        {
+            self.buckets.pop_front();
+        }
+    }
+
+    /// Average events per second over the window ending at `now`.
+    pub(crate) fn per_sec(&self, now: i64) -> f64 {
+        let second = now.div_euclid(1000);
+        let total: u64 = self
+            .buckets
+            .iter()
+            .filter(|(bucket, _)| *bucket > second - RATE_WINDOW_SECS && *bucket <= second)
+            .map(|(_, count)| count)
+            .sum();
+        total as f64 / RATE_WINDOW_SECS as f64
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.buckets.clear();
+    }
+}
+
+/// Cumulative and recent counts of messages flowing through a queue
+#[derive(Default)]
+pub(crate) struct Throughput {
+    /// Messages added since the queue was created
+    pub(crate) enqueued: u64,
+    /// Messages deleted by consumers since the queue was created
+    pub(crate) acked: u64,
+    /// Deliveries returned to the queue since the queue was created
+    pub(crate) retried: u64,
+    pub(crate) enqueue_rate: RateWindow,
+    pub(crate) ack_rate: RateWindow,
+}
+
+impl Throughput {
+    pub(crate) fn record_enqueued(&mut self, now: i64, count: u64) {
+        self.enqueued += count;
+        self.enqueue_rate.record(now, count);
+    }
+
+    pub(crate) fn record_acked(&mut self, now: i64, count: u64) {
+        self.acked += count;
+        self.ack_rate.record(now, count);
+    }
+
+    /// Forgets the recent rates but keeps the cumulative counts, e.g. after
+    /// a replay whose events all happened "now".
+    pub(crate) fn reset_rates(&mut self) {
+        self.enqueue_rate.clear();
+        self.ack_rate.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_averages_over_window() {
+        let mut rate = RateWindow::default();
+        rate.record(1_000, 30);
+        rate.record(1_500, 30);
+        rate.record(2_000, 0);
+
+        assert_eq!(rate.per_sec(2_000), 1.0);
+        assert_eq!(rate.per_sec(60_999), 1.0);
+        assert_eq!(rate.per_sec(61_000), 0.0);
+    }
+
+    #[test]
+    fn test_rate_drops_old_buckets() {
+        let mut rate = RateWindow::default();
+        rate.record(0, 60);
+        rate.record(30_000, 60);
+        rate.record(70_000, 60);
+
+        assert_eq!(rate.buckets.len(), 2);
+        assert_eq!(rate.per_sec(70_000), 2.0);
+    }
+
+    #[test]
+    fn test_reset_rates_keeps_totals() {
+        let mut throughput = Throughput::default();
+        throughput.record_enqueued(0, 5);
+        throughput.record_acked(0, 3);
+        throughput.reset_rates();
+
+        assert_eq!(throughput.enqueued, 5);
+        assert_eq!(throughput.acked, 3);
+        assert_eq!(throughput.enqueue_rate.per_sec(0), 0.0);
+    }
+}
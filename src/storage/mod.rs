@@ -1,2 +1,37 @@
+pub mod log;
 pub mod memory;
+pub mod notify;
 pub mod traits;
+
+use crate::errors::Error;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+pub(crate) fn queue_not_found(queue: &str) -> Error {
+    Error::NotFound(format!("Queue '{queue}' not found"))
+}
+
+/// Replaces the file at `path` with `contents`, so that a crash at any point
+/// leaves either the old or the new file in place.
+///
+/// The contents are written and synced to a temporary file next to `path`,
+/// which is then renamed over it. The directory is synced as well so the
+/// rename itself survives a crash. Returns the new file, open at its end.
+pub(crate) fn replace_file(path: &Path, contents: &[u8]) -> io::Result<File> {
+    let mut temp = path.as_os_str().to_owned();
+    temp.push(".tmp");
+    let temp = PathBuf::from(temp);
+
+    let mut file = File::create(&temp)?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+    fs::rename(&temp, path)?;
+
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    File::open(dir)?.sync_all()?;
+    Ok(file)
+}
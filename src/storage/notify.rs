@@ -0,0 +1,86 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::Notify;
+
+/// Wakes consumers waiting for messages to become ready on a queue.
+///
+/// Storage backends call [`QueueNotifier::notify`] whenever messages may have
+/// become ready; waiters obtained from [`QueueNotifier::subscribe`] are woken.
+#[derive(Default)]
+pub struct QueueNotifier {
+    queues: Mutex<HashMap<String, Arc<Notify>>>,
+}
+
+impl QueueNotifier {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the notifier of `queue`. Register interest with
+    /// [`Notify::notified`] before checking for messages to avoid missing a wake-up.
+    pub fn subscribe(&self, queue: &str) -> Arc<Notify> {
+        self.queues
+            .lock()
+            .unwrap()
+            .entry(queue.to_string())
+            .or_default()
+            .clone()
+    }
+
+    /// Wakes every consumer currently waiting on `queue`.
+    pub fn notify(&self, queue: &str) {
+        if let Some(notify) = self.queues.lock().unwrap().get(queue) {
+            notify.notify_waiters();
+        }
+    }
+
+    /// Wakes the consumers waiting on a deleted queue and forgets it.
+    pub fn remove(&self, queue: &str) {
+        if let Some(notify) = self.queues.lock().unwrap().remove(queue) {
+            notify.notify_waiters();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tokio::time::timeout;
+
+    #[tokio::test]
+    async fn test_notify_wakes_subscribers_of_the_queue() {
+        let notifier = QueueNotifier::new();
+        let orders = notifier.subscribe("orders");
+        let emails = notifier.subscribe("emails");
+        let orders_notified = orders.notified();
+        let emails_notified = emails.notified();
+        tokio::pin!(orders_notified);
+        tokio::pin!(emails_notified);
+        orders_notified.as_mut().enable();
+        emails_notified.as_mut().enable();
+
+        notifier.notify("orders");
+
+        assert!(timeout(Duration::from_millis(50), orders_notified)
+            .await
+            .is_ok());
+        assert!(timeout(Duration::from_millis(50), emails_notified)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_remove_wakes_subscribers() {
+        let notifier = QueueNotifier::new();
+        let orders = notifier.subscribe("orders");
+        let notified = orders.notified();
+        tokio::pin!(notified);
+        notified.as_mut().enable();
+
+        notifier.remove("orders");
+
+        assert!(timeout(Duration::from_millis(50), notified).await.is_ok());
+        assert!(notifier.queues.lock().unwrap().is_empty());
+    }
+}
@@ -1,13 +1,93 @@
-use crate::types::{Message, QueueStats, ReapResult};
+use crate::errors::Error;
+use crate::types::{
+    DeadLetter, ImportSummary, Message, MessageState, ProcessingRestore, QueueConfig,
+    QueueConfigUpdate, QueueInfo, QueueStats, ReapResult, ReceiptOutcome, RedriveOutcome, Snapshot,
+};
 use async_trait::async_trait;
+use std::sync::Arc;
+use tokio::sync::Notify;
+use uuid::Uuid;
 
 #[async_trait]
 pub trait Storage: Send + Sync {
-    async fn add(&self, msg: Message) -> Result<(), String>;
-    async fn get(&self, count: usize) -> Result<Vec<Message>, String>;
-    async fn delete(&self, ids: Vec<String>) -> Result<(), String>;
-    async fn purge(&self) -> Result<(), String>;
-    async fn retry(&self, ids: Vec<String>) -> Result<(), String>;
-    async fn stats(&self) -> Result<QueueStats, String>;
-    async fn reap_expired(&self, max_retries: u32) -> Result<ReapResult, String>;
+    async fn create_queue(&self, queue: &str, config: QueueConfig) -> Result<(), Error>;
+    async fn delete_queue(&self, queue: &str) -> Result<(), Error>;
+    async fn list_queues(&self) -> Result<Vec<QueueInfo>, Error>;
+    /// Returns the queue configuration, or `None` if the queue does not exist.
+    async fn queue_config(&self, queue: &str) -> Result<Option<QueueConfig>, Error>;
+    async fn update_queue_config(
+        &self,
+        queue: &str,
+        update: QueueConfigUpdate,
+    ) -> Result<QueueConfig, Error>;
+    /// Adds a message, creating the queue with default settings if it does not exist yet.
+    /// Returns the added message, or the original message if an earlier add with the
+    /// same `dedup_id` is still within the queue's deduplication window.
+    async fn add(&self, queue: &str, msg: Message) -> Result<Message, Error>;
+    /// Adds several messages in order under a single lock acquisition, as `add`
+    /// does for each. Returns the added or original message of each, in order.
+    async fn add_batch(&self, queue: &str, msgs: Vec<Message>) -> Result<Vec<Message>, Error>;
+    /// Locks up to `count` ready messages for `lock_secs`, defaulting to the queue's lock duration.
+    async fn get(
+        &self,
+        queue: &str,
+        count: usize,
+        lock_secs: Option<u64>,
+    ) -> Result<Vec<Message>, Error>;
+    /// Deletes the messages whose leases are held by the given receipt handles,
+    /// reporting the outcome of each receipt.
+    async fn delete(
+        &self,
+        queue: &str,
+        receipts: Vec<String>,
+    ) -> Result<Vec<ReceiptOutcome>, Error>;
+    async fn purge(&self, queue: &str) -> Result<(), Error>;
+    /// Returns the messages whose leases are held by the given receipt handles
+    /// to the queue, reporting the outcome of each receipt.
+    async fn retry(&self, queue: &str, receipts: Vec<String>)
+        -> Result<Vec<ReceiptOutcome>, Error>;
+    /// Pushes the lock of the messages delivered with the given receipt handles
+    /// forward by `extend_secs`. Fails without changing any lock if a receipt's
+    /// lease is expired or superseded.
+    async fn extend(
+        &self,
+        queue: &str,
+        receipts: Vec<String>,
+        extend_secs: u64,
+    ) -> Result<Vec<Message>, Error>;
+    async fn stats(&self, queue: &str) -> Result<QueueStats, Error>;
+    /// Reaps expired locks across all queues, honoring each queue's `max_retries`.
+    async fn reap_expired(&self) -> Result<ReapResult, Error>;
+    /// Returns up to `limit` ready, scheduled and processing messages with an ID
+    /// greater than `after`, in ID order, without changing their state.
+    async fn browse(
+        &self,
+        queue: &str,
+        state: Option<MessageState>,
+        after: Option<Uuid>,
+        limit: usize,
+    ) -> Result<Vec<Message>, Error>;
+    /// Looks up a message by ID in any state, including dead letters, without its receipt.
+    async fn find(&self, queue: &str, id: String) -> Result<Option<Message>, Error>;
+    async fn list_dead(&self, queue: &str, limit: usize) -> Result<Vec<DeadLetter>, Error>;
+    async fn get_dead(&self, queue: &str, id: String) -> Result<Option<DeadLetter>, Error>;
+    /// Moves dead letters back to the queue, reporting for each ID whether it was redriven.
+    async fn redrive(&self, queue: &str, ids: Vec<String>) -> Result<Vec<RedriveOutcome>, Error>;
+    async fn purge_dead(&self, queue: &str) -> Result<(), Error>;
+    /// Returns the configuration, messages in any state and dead letters of a queue.
+    async fn export(&self, queue: &str) -> Result<Snapshot, Error>;
+    /// Restores the messages and dead letters of a snapshot into a queue, creating it
+    /// with the snapshot's configuration if it does not exist. Entries whose ID is
+    /// already in the queue are skipped.
+    async fn import(
+        &self,
+        queue: &str,
+        snapshot: Snapshot,
+        processing: ProcessingRestore,
+    ) -> Result<ImportSummary, Error>;
+    /// Makes every change so far durable. A no-op for storage without persistence.
+    async fn flush(&self) -> Result<(), Error>;
+    /// Returns a notifier woken whenever messages may have become ready in `queue`,
+    /// e.g. after an add, a retry or a reaper requeue.
+    fn subscribe(&self, queue: &str) -> Arc<Notify>;
 }
@@ -0,0 +1,28 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Current time in milliseconds since the Unix epoch.
+pub(crate) fn now_millis() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as i64
+}
+
+/// Converts a duration in seconds to milliseconds, saturating instead of overflowing.
+pub(crate) fn secs_to_millis(secs: u64) -> i64 {
+    secs.checked_mul(1000)
+        .and_then(|millis| i64::try_from(millis).ok())
+        .unwrap_or(i64::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_secs_to_millis_saturates() {
+        assert_eq!(secs_to_millis(60), 60_000);
+        assert_eq!(secs_to_millis(u64::MAX / 1000), i64::MAX);
+        assert_eq!(secs_to_millis(u64::MAX), i64::MAX);
+    }
+}
@@ -1,15 +1,35 @@
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use uuid::Uuid;
 
+/// Name of the queue served by the unprefixed routes (`/add`, `/get`, ...).
+/// It always exists and cannot be deleted.
+pub const DEFAULT_QUEUE: &str = "default";
+
 /// Represents the current state of a message in the queue
 #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum MessageState {
     /// Message is available for processing
     Ready,
+    /// Message is waiting for its delivery time and cannot be retrieved yet
+    Scheduled,
     /// Message is locked and being processed by a consumer
     Processing,
     /// Message has been processed and can be removed from the queue
     Done,
+    /// Message exhausted its retries and was moved to the dead-letter queue
+    Dead,
+}
+
+/// How the `body` of a message is encoded
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BodyEncoding {
+    /// The body is plain UTF-8 text
+    #[default]
+    Text,
+    /// The body is binary data encoded as standard, padded base64
+    Base64,
 }
 
 /// Represents a message in the queue system.
@@ -18,8 +38,11 @@ pub enum MessageState {
 pub struct Message {
     /// Unique identifier with embedded timestamp (UUID v7)
     pub id: Uuid,
-    /// Content of the message
+    /// Content of the message, encoded as described by `encoding`
     pub body: String,
+    /// Encoding of `body`
+    #[serde(default)]
+    pub encoding: BodyEncoding,
     /// Current state of the message
     pub state: MessageState,
     /// Unix timestamp in milliseconds when the message lock expires.
@@ -27,6 +50,56 @@ pub struct Message {
     pub lock_until: Option<i64>,
     /// Number of processing attempts made on this message
     pub retry_count: i32,
+    /// Unix timestamp in milliseconds before which the message is not delivered.
+    /// None means the message was available immediately
+    #[serde(default)]
+    pub deliver_at: Option<i64>,
+    /// Delivery priority; higher values are delivered first
+    #[serde(default)]
+    pub priority: i32,
+    /// Unix timestamp in milliseconds after which an unconsumed message expires.
+    /// None means the message never expires
+    #[serde(default)]
+    pub expires_at: Option<i64>,
+    /// Receipt handle of the current delivery, required to delete, retry or extend it.
+    /// None means the message is not locked
+    #[serde(default)]
+    pub receipt: Option<String>,
+    /// String attributes set by the producer, e.g. content type or correlation id
+    #[serde(default)]
+    pub attributes: BTreeMap<String, String>,
+    /// Producer-chosen key identifying duplicate adds of this message
+    #[serde(default)]
+    pub dedup_id: Option<String>,
+    /// Ordering group; a message is not delivered while an earlier message
+    /// of the same group is processing
+    #[serde(default)]
+    pub group_id: Option<String>,
+}
+
+/// Optional settings for a message being added
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AddOptions {
+    /// Seconds to wait before the message becomes available
+    pub delay_secs: Option<u64>,
+    /// Unix timestamp in milliseconds at which the message becomes available
+    pub deliver_at: Option<i64>,
+    /// Delivery priority; higher values are delivered first. Defaults to 0
+    pub priority: Option<i32>,
+    /// Seconds the message may wait for a consumer once it is deliverable.
+    /// Defaults to the queue's `ttl_secs`
+    pub ttl_secs: Option<u64>,
+    /// String attributes stored with the message, e.g. content type or correlation id
+    #[serde(default)]
+    pub attributes: BTreeMap<String, String>,
+    /// Encoding of the body. Defaults to plain text
+    #[serde(default)]
+    pub encoding: BodyEncoding,
+    /// Key identifying retries of the same add; a repeated key within the queue's
+    /// deduplication window returns the original message
+    pub dedup_id: Option<String>,
+    /// Ordering group; messages of a group are delivered one at a time, in order
+    pub group_id: Option<String>,
 }
 
 /// Queue statistics showing the number of messages in each state
@@ -36,14 +109,225 @@ pub struct QueueStats {
     pub ready: usize,
     /// Number of messages currently being processed
     pub processing: usize,
-    /// Cumulative count of messages removed by the reaper after exceeding max retries
+    /// Number of messages waiting for their delivery time
+    pub scheduled: usize,
+    /// Number of messages in the dead-letter queue
     pub dead: usize,
+    /// Number of messages that expired before being consumed
+    pub expired: usize,
+    /// Milliseconds since the oldest ready message was added
+    pub oldest_ready_age_ms: Option<i64>,
+    /// Earliest lock expiry of a processing message, as a Unix timestamp in milliseconds
+    pub oldest_lock_until: Option<i64>,
+    /// Total size in bytes of ready, scheduled and processing messages
+    pub bytes: usize,
+    /// Number of messages added since the queue was created
+    pub enqueued: u64,
+    /// Number of messages deleted by consumers since the queue was created
+    pub acked: u64,
+    /// Number of deliveries returned to the queue, by consumers or the reaper,
+    /// since the queue was created
+    pub retried: u64,
+    /// Messages added per second over the last minute
+    pub enqueue_rate: f64,
+    /// Messages deleted per second over the last minute
+    pub ack_rate: f64,
+}
+
+/// Per-queue settings. Queues created without explicit values inherit the
+/// server-wide defaults from the environment configuration.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct QueueConfig {
+    /// Seconds a message stays locked after being retrieved
+    pub lock_duration_secs: u64,
+    /// Retries allowed before an expired message is moved to the dead-letter queue
+    pub max_retries: u32,
+    /// Maximum message body size in bytes
+    pub max_message_size: usize,
+    /// Default time-to-live of new messages in seconds; 0 means messages never expire
+    #[serde(default)]
+    pub ttl_secs: u64,
+    /// Whether expired messages are moved to the dead-letter queue instead of being dropped
+    #[serde(default)]
+    pub dead_letter_expired: bool,
+    /// Seconds during which an add with a known `dedup_id` returns the original message;
+    /// 0 disables deduplication
+    #[serde(default = "default_dedup_window_secs")]
+    pub dedup_window_secs: u64,
+}
+
+/// Deduplication window of configurations recorded before the setting existed
+fn default_dedup_window_secs() -> u64 {
+    crate::config::config().dedup_window_secs
+}
+
+/// A partial change to a [`QueueConfig`]; unset fields keep their current value
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct QueueConfigUpdate {
+    pub lock_duration_secs: Option<u64>,
+    pub max_retries: Option<u32>,
+    pub max_message_size: Option<usize>,
+    pub ttl_secs: Option<u64>,
+    pub dead_letter_expired: Option<bool>,
+    pub dedup_window_secs: Option<u64>,
+}
+
+impl QueueConfig {
+    /// Returns a copy of this configuration with the fields set in `update` replaced.
+    pub fn merge(mut self, update: &QueueConfigUpdate) -> QueueConfig {
+        if let Some(lock_duration_secs) = update.lock_duration_secs {
+            self.lock_duration_secs = lock_duration_secs;
+        }
+        if let Some(max_retries) = update.max_retries {
+            self.max_retries = max_retries;
+        }
+        if let Some(max_message_size) = update.max_message_size {
+            self.max_message_size = max_message_size;
+        }
+        if let Some(ttl_secs) = update.ttl_secs {
+            self.ttl_secs = ttl_secs;
+        }
+        if let Some(dead_letter_expired) = update.dead_letter_expired {
+            self.dead_letter_expired = dead_letter_expired;
+        }
+        if let Some(dedup_window_secs) = update.dedup_window_secs {
+            self.dedup_window_secs = dedup_window_secs;
+        }
+        self
+    }
+}
+
+/// A named queue together with its current statistics and configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueueInfo {
+    /// Name of the queue
+    pub name: String,
+    /// Current statistics of the queue
+    #[serde(flatten)]
+    pub stats: QueueStats,
+    /// Settings applied to the queue
+    pub config: QueueConfig,
 }
 
+/// Why a message was moved to the dead-letter queue
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeadReason {
+    /// The message lock expired after the message had used up its retries
+    MaxRetriesExceeded,
+    /// The message was not consumed before its time-to-live ran out
+    Expired,
+}
+
+/// What happened to a message addressed by a receipt in a delete or retry request
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReceiptStatus {
+    /// The message was deleted
+    Deleted,
+    /// The message was returned to the queue
+    Retried,
+    /// No message with this ID exists, e.g. it was already deleted
+    NotFound,
+    /// The message exists but is not locked by a consumer
+    NotProcessing,
+    /// The lease of the receipt ran out; the message may have been redelivered
+    LockExpired,
+}
+
+/// Result of a delete or retry for a single receipt
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReceiptOutcome {
+    /// ID of the message the receipt was issued for, if the receipt is well-formed
+    pub id: Option<Uuid>,
+    pub receipt: String,
+    pub status: ReceiptStatus,
+}
+
+/// What happened to a dead letter addressed in a redrive request
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RedriveStatus {
+    /// The dead letter was moved back to the queue
+    Redriven,
+    /// No dead letter with this ID exists, e.g. it was already redriven or purged
+    NotFound,
+}
+
+/// Result of a redrive for a single message ID
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RedriveOutcome {
+    pub id: String,
+    pub status: RedriveStatus,
+}
+
+/// A page of messages returned by a browse
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MessagePage {
+    pub messages: Vec<Message>,
+    /// Cursor to pass as `after` to fetch the next page; `None` on the last page
+    pub next_cursor: Option<Uuid>,
+}
+
+/// Result of adding a single message of a batch
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AddOutcome {
+    /// The added message, or the original message of a duplicate add; `None` if rejected
+    pub message: Option<Message>,
+    /// Why the message was rejected
+    pub error: Option<String>,
+}
+
+/// A message held in the dead-letter queue
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeadLetter {
+    /// The message as it was when it died
+    #[serde(flatten)]
+    pub message: Message,
+    /// Why the message was moved to the dead-letter queue
+    pub reason: DeadReason,
+    /// Unix timestamp in milliseconds of the last failure
+    pub failed_at: i64,
+}
+
+/// How messages that were processing when a snapshot was taken are restored
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProcessingRestore {
+    /// Return them to the queue for redelivery; their receipts become invalid
+    #[default]
+    Requeue,
+    /// Keep them locked until their original `lock_until`, so their receipts stay valid
+    Keep,
+}
+
+/// Contents of a queue, as exported to and imported from a snapshot
 #[derive(Debug, Clone)]
+pub struct Snapshot {
+    pub config: QueueConfig,
+    /// Ready, scheduled and processing messages, in ID order
+    pub messages: Vec<Message>,
+    pub dead_letters: Vec<DeadLetter>,
+}
+
+/// Result of importing a snapshot into a queue
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImportSummary {
+    /// Number of messages added to the queue
+    pub imported: usize,
+    /// Number of dead letters added to the dead-letter queue
+    pub dead_letters: usize,
+    /// Number of messages and dead letters skipped because their ID is already
+    /// in the queue or their state cannot be restored
+    pub skipped: usize,
+}
+
+#[derive(Debug, Clone, Default)]
 pub struct ReapResult {
     pub retried: usize,
     pub dead: usize,
+    pub promoted: usize,
+    pub expired: usize,
 }
 
 impl Message {
@@ -53,6 +337,8 @@ impl Message {
     /// - Ready state
     /// - No lock
     /// - Zero retry count
+    /// - Default priority (0)
+    /// - No expiry
     ///
     /// # Arguments
     ///
@@ -71,9 +357,85 @@ impl Message {
         Message {
             id: Uuid::now_v7(),
             body,
+            encoding: BodyEncoding::Text,
             state: MessageState::Ready,
             lock_until: None,
             retry_count: 0,
+            deliver_at: None,
+            priority: 0,
+            expires_at: None,
+            receipt: None,
+            attributes: BTreeMap::new(),
+            dedup_id: None,
+            group_id: None,
+        }
+    }
+
+    /// Size counted against the queue's `max_message_size`: the decoded body
+    /// plus every attribute name and value, in bytes.
+    pub fn size(&self) -> usize {
+        self.body_len()
+            + self
+                .attributes
+                .iter()
+                .map(|(name, value)| name.len() + value.len())
+                .sum::<usize>()
+    }
+
+    /// Unix timestamp in milliseconds at which the message was created,
+    /// taken from its UUID v7 identifier.
+    pub fn created_at(&self) -> i64 {
+        self.id
+            .get_timestamp()
+            .map(|ts| {
+                let (secs, nanos) = ts.to_unix();
+                secs as i64 * 1000 + (nanos / 1_000_000) as i64
+            })
+            .unwrap_or_default()
+    }
+
+    /// Length of the decoded body in bytes.
+    fn body_len(&self) -> usize {
+        match self.encoding {
+            BodyEncoding::Text => self.body.len(),
+            BodyEncoding::Base64 => {
+                let padding = self.body.bytes().rev().take_while(|&b| b == b'=').count();
+                (self.body.len() / 4 * 3).saturating_sub(padding)
+            }
+        }
+    }
+
+    /// Issues a new receipt handle for a delivery of this message.
+    /// Handles have the form `<message id>.<delivery token>`.
+    pub fn new_receipt(&self) -> String {
+        format!("{}.{}", self.id, Uuid::now_v7().simple())
+    }
+
+    /// Returns a copy of the message without its receipt, for read-only views:
+    /// whoever holds the receipt can delete or retry the current delivery.
+    pub fn without_receipt(&self) -> Message {
+        Message {
+            receipt: None,
+            ..self.clone()
+        }
+    }
+
+    /// Returns the ID of the message a receipt handle was issued for,
+    /// or None if the handle is malformed.
+    pub fn receipt_id(receipt: &str) -> Option<Uuid> {
+        let (id, token) = receipt.split_once('.')?;
+        if token.is_empty() {
+            return None;
+        }
+        Uuid::parse_str(id).ok()
+    }
+
+    /// Delays delivery of the message until `deliver_at` (Unix timestamp in milliseconds).
+    /// A message whose delivery time is not after `now` stays Ready.
+    pub fn schedule(&mut self, deliver_at: i64, now: i64) {
+        self.deliver_at = Some(deliver_at);
+        if deliver_at > now {
+            self.state = MessageState::Scheduled;
         }
     }
 }
@@ -81,6 +443,36 @@ impl Message {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::time::now_millis;
+
+    #[test]
+    fn test_queue_config_merge() {
+        let config = QueueConfig {
+            lock_duration_secs: 60,
+            max_retries: 3,
+            max_message_size: 1024,
+            ttl_secs: 0,
+            dead_letter_expired: false,
+            dedup_window_secs: 300,
+        };
+
+        let merged = config.merge(&QueueConfigUpdate {
+            lock_duration_secs: Some(300),
+            max_retries: None,
+            max_message_size: Some(2048),
+            ttl_secs: Some(3600),
+            dead_letter_expired: None,
+            dedup_window_secs: Some(0),
+        });
+        assert_eq!(merged.lock_duration_secs, 300);
+        assert_eq!(merged.max_retries, 3);
+        assert_eq!(merged.max_message_size, 2048);
+        assert_eq!(merged.ttl_secs, 3600);
+        assert!(!merged.dead_letter_expired);
+        assert_eq!(merged.dedup_window_secs, 0);
+
+        assert_eq!(config.merge(&QueueConfigUpdate::default()), config);
+    }
 
     #[test]
     fn test_message_new() {
@@ -89,4 +481,60 @@ mod tests {
         assert!(matches!(msg.state, MessageState::Ready));
         assert_eq!(msg.retry_count, 0);
     }
+
+    #[test]
+    fn test_message_size_includes_attributes() {
+        let mut msg = Message::new("Hello".to_string());
+        assert_eq!(msg.size(), 5);
+
+        msg.attributes
+            .insert("content-type".to_string(), "text/plain".to_string());
+        assert_eq!(msg.size(), 5 + 12 + 10);
+    }
+
+    #[test]
+    fn test_message_size_uses_decoded_body() {
+        let mut msg = Message::new("AAECAw==".to_string());
+        msg.encoding = BodyEncoding::Base64;
+        assert_eq!(msg.size(), 4);
+
+        msg.body = "AAECAwQ=".to_string();
+        assert_eq!(msg.size(), 5);
+
+        msg.body = "AAECAwQF".to_string();
+        assert_eq!(msg.size(), 6);
+    }
+
+    #[test]
+    fn test_created_at() {
+        let before = now_millis();
+        let msg = Message::new("Hello world".to_string());
+        assert!(msg.created_at() >= before);
+        assert!(msg.created_at() <= before + 1000);
+    }
+
+    #[test]
+    fn test_receipt_id() {
+        let msg = Message::new("Hello world".to_string());
+        let receipt = msg.new_receipt();
+        assert_eq!(Message::receipt_id(&receipt), Some(msg.id));
+        assert_ne!(msg.new_receipt(), receipt);
+
+        assert_eq!(Message::receipt_id(&msg.id.to_string()), None);
+        assert_eq!(Message::receipt_id(&format!("{}.", msg.id)), None);
+        assert_eq!(Message::receipt_id("not-a-uuid.token"), None);
+    }
+
+    #[test]
+    fn test_message_schedule() {
+        let mut msg = Message::new("later".to_string());
+        msg.schedule(2000, 1000);
+        assert_eq!(msg.state, MessageState::Scheduled);
+        assert_eq!(msg.deliver_at, Some(2000));
+
+        let mut msg = Message::new("now".to_string());
+        msg.schedule(1000, 1000);
+        assert_eq!(msg.state, MessageState::Ready);
+        assert_eq!(msg.deliver_at, Some(1000));
+    }
 }
@@ -1,23 +1,98 @@
+use crate::metrics::metrics;
+use crate::storage::memory::MemoryStorage;
 use crate::storage::traits::Storage;
 use std::sync::Arc;
 use std::time::Duration;
+use tokio::sync::watch;
 use tracing::{info, warn};
 
-pub async fn start_reaper(storage: Arc<dyn Storage>, max_retries: u32, interval_secs: u64) {
+/// Runs the reaper every `interval_secs` until `shutdown` turns true. A reaper
+/// step in progress is always completed before stopping.
+pub async fn start_reaper(
+    storage: Arc<dyn Storage>,
+    interval_secs: u64,
+    mut shutdown: watch::Receiver<bool>,
+) {
     let mut interval = tokio::time::interval(Duration::from_secs(interval_secs));
 
     loop {
-        interval.tick().await;
-
-        match storage.reap_expired(max_retries).await {
-            Ok(result) if result.retried > 0 || result.dead > 0 => {
-                info!(
-                    "Reaper: retried={}, removed={}",
-                    result.retried, result.dead
-                );
+        tokio::select! {
+            _ = interval.tick() => {}
+            _ = shutdown.wait_for(|stopping| *stopping) => {
+                info!("Reaper stopped");
+                return;
+            }
+        }
+
+        match storage.reap_expired().await {
+            Ok(result) => {
+                metrics().reaper_retried.inc_by(result.retried as u64);
+                metrics().reaper_dead.inc_by(result.dead as u64);
+                if result.retried > 0
+                    || result.dead > 0
+                    || result.promoted > 0
+                    || result.expired > 0
+                {
+                    info!(
+                        "Reaper: retried={}, dead={}, promoted={}, expired={}",
+                        result.retried, result.dead, result.promoted, result.expired
+                    );
+                }
             }
             Err(e) => warn!("Reaper error: {}", e),
-            _ => {}
         }
     }
 }
+
+/// Writes a snapshot of `storage` every `interval_secs` until `shutdown` turns
+/// true. The final snapshot is left to the storage flush on shutdown.
+pub async fn start_snapshots(
+    storage: Arc<MemoryStorage>,
+    interval_secs: u64,
+    mut shutdown: watch::Receiver<bool>,
+) {
+    let period = Duration::from_secs(interval_secs);
+    let mut interval = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {}
+            _ = shutdown.wait_for(|stopping| *stopping) => {
+                info!("Snapshots stopped");
+                return;
+            }
+        }
+
+        if let Err(e) = storage.save().await {
+            warn!("Snapshot error: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::time::timeout;
+
+    #[tokio::test]
+    async fn test_reaper_stops_on_shutdown() {
+        let (shutdown_tx, shutdown_rx) = watch::channel(false);
+        let reaper = tokio::spawn(start_reaper(Arc::new(MemoryStorage::new()), 1, shutdown_rx));
+
+        shutdown_tx.send_replace(true);
+        assert!(timeout(Duration::from_secs(1), reaper).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_snapshots_stop_on_shutdown() {
+        let (shutdown_tx, shutdown_rx) = watch::channel(false);
+        let snapshots = tokio::spawn(start_snapshots(
+            Arc::new(MemoryStorage::new()),
+            1,
+            shutdown_rx,
+        ));
+
+        shutdown_tx.send_replace(true);
+        assert!(timeout(Duration::from_secs(1), snapshots).await.is_ok());
+    }
+}
@@ -36,6 +36,29 @@ pub fn create_post_request(path: &str, body_content: serde_json::Value) -> Reque
         .unwrap()
 }
 
+/// Creates a POST request with the specified path and a raw binary body.
+///
+/// # Arguments
+///
+/// * `path` - The URI path for the request (e.g., "/add/binary")
+/// * `body_content` - The bytes to be sent in the request body
+///
+/// # Returns
+///
+/// An HTTP POST request with octet-stream content type and the provided body
+#[cfg(test)]
+pub fn create_binary_post_request(path: &str, body_content: Vec<u8>) -> Request<Body> {
+    Request::builder()
+        .uri(path)
+        .method(http::Method::POST)
+        .header(
+            http::header::CONTENT_TYPE,
+            mime::APPLICATION_OCTET_STREAM.as_ref(),
+        )
+        .body(Body::from(body_content))
+        .unwrap()
+}
+
 /// Creates a GET request with the specified path.
 ///
 /// # Arguments
@@ -55,6 +78,25 @@ pub fn create_get_request(path: &str) -> Request<Body> {
         .unwrap()
 }
 
+/// Creates a DELETE request with the specified path.
+///
+/// # Arguments
+///
+/// * `path` - The URI path for the request (e.g., "/queues/orders")
+///
+/// # Returns
+///
+/// An HTTP DELETE request with JSON content type and empty body
+#[cfg(test)]
+pub fn create_delete_request(path: &str) -> Request<Body> {
+    Request::builder()
+        .uri(path)
+        .method(http::Method::DELETE)
+        .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+        .body(Body::empty())
+        .unwrap()
+}
+
 /// Sends an HTTP request to the application router and returns the response.
 ///
 /// # Arguments
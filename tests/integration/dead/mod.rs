@@ -0,0 +1,78 @@
+use crate::common::{create_get_request, create_post_request, send_request, setup_test_app};
+use http::StatusCode;
+use http_body_util::BodyExt;
+use serde_json::json;
+use uuid::Uuid;
+
+#[tokio::test]
+async fn test_list_dead_letters_empty() {
+    let mut app = setup_test_app().into_service();
+
+    let response = send_request(&mut app, create_get_request("/dead")).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body_json = serde_json::from_slice::<serde_json::Value>(&body).unwrap();
+    assert_eq!(body_json, json!([]));
+}
+
+#[tokio::test]
+async fn test_get_unknown_dead_letter_returns_not_found() {
+    let mut app = setup_test_app().into_service();
+
+    let path = format!("/dead/{}", Uuid::now_v7());
+    let response = send_request(&mut app, create_get_request(&path)).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body_text = String::from_utf8(body.to_vec()).unwrap();
+    assert_eq!(body_text, "Dead letter not found");
+}
+
+#[tokio::test]
+async fn test_get_dead_letter_with_invalid_id() {
+    let mut app = setup_test_app().into_service();
+
+    let response = send_request(&mut app, create_get_request("/dead/invalid-id")).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_redrive_with_invalid_ids() {
+    let mut app = setup_test_app().into_service();
+
+    let request = create_post_request("/dead/redrive", json!({"ids": ["invalid-id"]}));
+    let response = send_request(&mut app, request).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body_text = String::from_utf8(body.to_vec()).unwrap();
+    assert_eq!(body_text, "Invalid message IDs: [\"invalid-id\"]");
+}
+
+#[tokio::test]
+async fn test_redrive_reports_unknown_ids() {
+    let mut app = setup_test_app().into_service();
+
+    let id = Uuid::now_v7().to_string();
+    let request = create_post_request("/dead/redrive", json!({"ids": [id]}));
+    let response = send_request(&mut app, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let outcomes: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(outcomes, json!([{"id": id, "status": "not_found"}]));
+}
+
+#[tokio::test]
+async fn test_purge_dead_letters() {
+    let mut app = setup_test_app().into_service();
+
+    let request = create_post_request("/dead/purge", json!({}));
+    let response = send_request(&mut app, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let result: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(result, json!("Success"));
+}
@@ -1,7 +1,13 @@
-use crate::common::{create_post_request, setup_test_app};
-use http::StatusCode;
+use crate::common::{
+    create_binary_post_request, create_get_request, create_post_request, send_request,
+    setup_test_app,
+};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use http::{HeaderValue, StatusCode};
 use http_body_util::BodyExt;
 use serde_json::json;
+use tlq::types::{BodyEncoding, Message};
 use tower::ServiceExt;
 
 #[tokio::test]
@@ -25,7 +31,7 @@ async fn test_valid_message_returns_success_response() {
 }
 
 #[tokio::test]
-async fn test_message_with_body_exceeding_size_limit_returns_bad_request() {
+async fn test_message_with_body_exceeding_size_limit_returns_payload_too_large() {
     let app = setup_test_app();
 
     let large_body = "a".repeat(65537);
@@ -35,9 +41,260 @@ async fn test_message_with_body_exceeding_size_limit_returns_bad_request() {
         .await
         .unwrap();
 
-    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
 
     let body = response.into_body().collect().await.unwrap().to_bytes();
     let body_text = String::from_utf8(body.to_vec()).unwrap();
     assert_eq!(body_text, "Message body size is too large");
 }
+
+#[tokio::test]
+async fn test_delayed_message_is_scheduled() {
+    let mut app = setup_test_app().into_service();
+
+    let request = create_post_request("/add", json!({"body": "later", "delay_secs": 60}));
+    let response = send_request(&mut app, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body_json = serde_json::from_slice::<serde_json::Value>(&body).unwrap();
+    assert_eq!(body_json["state"], json!("Scheduled"));
+    assert!(body_json["deliver_at"].is_i64());
+
+    let request = create_post_request("/get", json!({"count": 1}));
+    let response = send_request(&mut app, request).await;
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body_json = serde_json::from_slice::<serde_json::Value>(&body).unwrap();
+    assert_eq!(body_json, json!([]));
+
+    let response = send_request(&mut app, create_get_request("/stats")).await;
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body_json = serde_json::from_slice::<serde_json::Value>(&body).unwrap();
+    assert_eq!(body_json["ready"], json!(0));
+    assert_eq!(body_json["scheduled"], json!(1));
+}
+
+#[tokio::test]
+async fn test_message_with_past_deliver_at_is_ready() {
+    let app = setup_test_app();
+
+    let response = app
+        .oneshot(create_post_request(
+            "/add",
+            json!({"body": "now", "deliver_at": 1000}),
+        ))
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body_json = serde_json::from_slice::<serde_json::Value>(&body).unwrap();
+    assert_eq!(body_json["state"], json!("Ready"));
+    assert_eq!(body_json["deliver_at"], json!(1000));
+}
+
+#[tokio::test]
+async fn test_message_with_delay_and_deliver_at_returns_bad_request() {
+    let app = setup_test_app();
+
+    let response = app
+        .oneshot(create_post_request(
+            "/add",
+            json!({"body": "both", "delay_secs": 1, "deliver_at": 1000}),
+        ))
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body_text = String::from_utf8(body.to_vec()).unwrap();
+    assert_eq!(
+        body_text,
+        "Only one of delay_secs and deliver_at can be set"
+    );
+}
+
+#[tokio::test]
+async fn test_message_with_ttl_has_expiry() {
+    let app = setup_test_app();
+
+    let response = app
+        .oneshot(create_post_request(
+            "/add",
+            json!({"body": "short-lived", "ttl_secs": 30}),
+        ))
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body_json = serde_json::from_slice::<serde_json::Value>(&body).unwrap();
+    assert!(body_json["expires_at"].is_i64());
+}
+
+#[tokio::test]
+async fn test_message_with_zero_ttl_returns_bad_request() {
+    let app = setup_test_app();
+
+    let response = app
+        .oneshot(create_post_request(
+            "/add",
+            json!({"body": "short-lived", "ttl_secs": 0}),
+        ))
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_binary_message_is_returned_as_base64() {
+    let mut app = setup_test_app().into_service();
+
+    let payload = vec![0u8, 159, 146, 150, 255];
+    let request = create_binary_post_request("/add/binary?priority=3", payload.clone());
+    let response = send_request(&mut app, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body_json = serde_json::from_slice::<serde_json::Value>(&body).unwrap();
+    assert_eq!(body_json["encoding"], json!("base64"));
+    assert_eq!(body_json["priority"], json!(3));
+
+    let get_request = create_post_request("/get", json!({"count": 1}));
+    let response = send_request(&mut app, get_request).await;
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let messages = serde_json::from_slice::<Vec<Message>>(&body).unwrap();
+    assert_eq!(messages[0].encoding, BodyEncoding::Base64);
+    assert_eq!(BASE64.decode(&messages[0].body).unwrap(), payload);
+}
+
+#[tokio::test]
+async fn test_binary_message_takes_attributes_from_headers() {
+    let mut app = setup_test_app().into_service();
+
+    let mut request = create_binary_post_request("/add/binary", vec![1, 2, 3]);
+    let headers = request.headers_mut();
+    headers.insert(
+        "X-TLQ-Attribute-Content-Type",
+        HeaderValue::from_static("application/x-protobuf"),
+    );
+    headers.insert("x-tlq-attribute-trace", HeaderValue::from_static("abc"));
+    let response = send_request(&mut app, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body_json = serde_json::from_slice::<serde_json::Value>(&body).unwrap();
+    assert_eq!(
+        body_json["attributes"],
+        json!({"content-type": "application/x-protobuf", "trace": "abc"})
+    );
+
+    let mut request = create_binary_post_request("/add/binary", vec![1, 2, 3]);
+    request.headers_mut().insert(
+        "x-tlq-attribute-name",
+        HeaderValue::from_bytes("caf\u{e9}".as_bytes()).unwrap(),
+    );
+    let response = send_request(&mut app, request).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_binary_message_size_limit_applies_to_decoded_body() {
+    let mut app = setup_test_app().into_service();
+
+    // 65536 bytes encode to more than 65536 base64 characters
+    let request = create_binary_post_request("/add/binary", vec![7u8; 65536]);
+    let response = send_request(&mut app, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let request = create_binary_post_request("/add/binary", vec![7u8; 65537]);
+    let response = send_request(&mut app, request).await;
+    assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+}
+
+#[tokio::test]
+async fn test_base64_message_with_invalid_body_returns_bad_request() {
+    let app = setup_test_app();
+
+    let response = app
+        .oneshot(create_post_request(
+            "/add",
+            json!({"body": "not base64!", "encoding": "base64"}),
+        ))
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_message_with_repeated_dedup_id_returns_original() {
+    let mut app = setup_test_app().into_service();
+
+    let request = create_post_request("/add", json!({"body": "charge", "dedup_id": "payment-7"}));
+    let response = send_request(&mut app, request).await;
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let original = serde_json::from_slice::<Message>(&body).unwrap();
+    assert_eq!(original.dedup_id.as_deref(), Some("payment-7"));
+
+    let request = create_post_request("/add", json!({"body": "charge", "dedup_id": "payment-7"}));
+    let response = send_request(&mut app, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let duplicate = serde_json::from_slice::<Message>(&body).unwrap();
+    assert_eq!(duplicate.id, original.id);
+
+    let response = send_request(&mut app, create_get_request("/stats")).await;
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let stats = serde_json::from_slice::<serde_json::Value>(&body).unwrap();
+    assert_eq!(stats["ready"], json!(1));
+}
+
+#[tokio::test]
+async fn test_batch_returns_outcome_per_message() {
+    let mut app = setup_test_app().into_service();
+
+    let request = create_post_request(
+        "/add/batch",
+        json!({"messages": [
+            {"body": "first", "priority": 5},
+            {"body": "a".repeat(65537)},
+            {"body": "second", "delay_secs": 0, "deliver_at": 1},
+        ]}),
+    );
+    let response = send_request(&mut app, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let outcomes = serde_json::from_slice::<serde_json::Value>(&body).unwrap();
+    assert_eq!(outcomes[0]["message"]["body"], json!("first"));
+    assert_eq!(outcomes[0]["message"]["priority"], json!(5));
+    assert_eq!(outcomes[0]["error"], json!(null));
+    assert_eq!(outcomes[1]["message"], json!(null));
+    assert_eq!(
+        outcomes[1]["error"],
+        json!("Message body size is too large")
+    );
+    assert!(outcomes[2]["error"].is_string());
+
+    let response = send_request(&mut app, create_get_request("/stats")).await;
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let stats = serde_json::from_slice::<serde_json::Value>(&body).unwrap();
+    assert_eq!(stats["ready"], json!(1));
+}
+
+#[tokio::test]
+async fn test_empty_batch_returns_bad_request() {
+    let app = setup_test_app();
+
+    let response = app
+        .oneshot(create_post_request("/add/batch", json!({"messages": []})))
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
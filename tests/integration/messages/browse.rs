@@ -0,0 +1,101 @@
+use crate::common::{create_get_request, create_post_request, send_request, setup_test_app};
+use http::StatusCode;
+use http_body_util::BodyExt;
+use serde_json::json;
+use tlq::types::{Message, MessagePage, MessageState};
+
+#[tokio::test]
+async fn test_browse_messages_does_not_lock_them() {
+    let mut app = setup_test_app().into_service();
+
+    for i in 1..=3 {
+        let post_request = create_post_request("/add", json!({"body": format!("message {}", i)}));
+        send_request(&mut app, post_request).await;
+    }
+    send_request(&mut app, create_post_request("/get", json!({"count": 1}))).await;
+
+    let response = send_request(&mut app, create_get_request("/messages?limit=2")).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let page = serde_json::from_slice::<MessagePage>(&body).unwrap();
+    assert_eq!(page.messages.len(), 2);
+    assert_eq!(page.messages[0].state, MessageState::Processing);
+    assert_eq!(page.messages[1].state, MessageState::Ready);
+
+    let uri = format!("/messages?limit=2&after={}", page.next_cursor.unwrap());
+    let response = send_request(&mut app, create_get_request(&uri)).await;
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let page = serde_json::from_slice::<MessagePage>(&body).unwrap();
+    assert_eq!(page.messages.len(), 1);
+    assert_eq!(page.messages[0].body, "message 3");
+    assert!(page.next_cursor.is_none());
+
+    let response = send_request(&mut app, create_get_request("/messages?state=Ready")).await;
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let page = serde_json::from_slice::<MessagePage>(&body).unwrap();
+    assert_eq!(page.messages.len(), 2);
+
+    let response = send_request(&mut app, create_get_request("/stats")).await;
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let stats = serde_json::from_slice::<serde_json::Value>(&body).unwrap();
+    assert_eq!(stats["ready"], json!(2));
+    assert_eq!(stats["processing"], json!(1));
+}
+
+#[tokio::test]
+async fn test_browse_unknown_queue_returns_not_found() {
+    let mut app = setup_test_app().into_service();
+
+    let response = send_request(&mut app, create_get_request("/queues/missing/messages")).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_browse_with_invalid_cursor_returns_bad_request() {
+    let mut app = setup_test_app().into_service();
+
+    let response = send_request(&mut app, create_get_request("/messages?after=abc")).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_get_message_by_id() {
+    let mut app = setup_test_app().into_service();
+
+    let response = send_request(
+        &mut app,
+        create_post_request("/queues/orders/add", json!({"body": "order 1"})),
+    )
+    .await;
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let added = serde_json::from_slice::<Message>(&body).unwrap();
+    send_request(
+        &mut app,
+        create_post_request("/queues/orders/get", json!({"count": 1})),
+    )
+    .await;
+
+    let uri = format!("/queues/orders/messages/{}", added.id);
+    let response = send_request(&mut app, create_get_request(&uri)).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let message = serde_json::from_slice::<Message>(&body).unwrap();
+    assert_eq!(message.id, added.id);
+    assert_eq!(message.body, "order 1");
+    assert_eq!(message.state, MessageState::Processing);
+    assert!(message.lock_until.is_some());
+    assert!(message.receipt.is_none());
+
+    // The message belongs to its queue only
+    let uri = format!("/messages/{}", added.id);
+    let response = send_request(&mut app, create_get_request(&uri)).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_get_message_with_invalid_id_returns_bad_request() {
+    let mut app = setup_test_app().into_service();
+
+    let response = send_request(&mut app, create_get_request("/messages/not-a-uuid")).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
@@ -2,7 +2,7 @@ use crate::common::{create_post_request, send_request, setup_test_app};
 use http::StatusCode;
 use http_body_util::BodyExt;
 use serde_json::json;
-use tlq::types::Message;
+use tlq::types::{Message, ReceiptOutcome, ReceiptStatus};
 
 #[tokio::test]
 async fn test_delete_messages() {
@@ -18,17 +18,34 @@ async fn test_delete_messages() {
     )
     .unwrap();
 
-    let delete_request = create_post_request("/delete", json!({"ids": [body[0].id]}));
+    let delete_request = create_post_request("/delete", json!({"receipts": [body[0].receipt]}));
     let response = send_request(&mut app, delete_request).await;
     assert_eq!(response.status(), StatusCode::OK);
+
+    let body_bytes = response.into_body().collect().await.unwrap().to_bytes();
+    let outcomes: Vec<ReceiptOutcome> = serde_json::from_slice(&body_bytes).unwrap();
+    assert_eq!(outcomes.len(), 1);
+    assert_eq!(outcomes[0].id, Some(body[0].id));
+    assert_eq!(outcomes[0].status, ReceiptStatus::Deleted);
+
+    // Deleting again reports the message as gone
+    let delete_request = create_post_request("/delete", json!({"receipts": [body[0].receipt]}));
+    let response = send_request(&mut app, delete_request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body_bytes = response.into_body().collect().await.unwrap().to_bytes();
+    let outcomes: Vec<ReceiptOutcome> = serde_json::from_slice(&body_bytes).unwrap();
+    assert_eq!(outcomes[0].status, ReceiptStatus::NotFound);
 }
 
 #[tokio::test]
-async fn test_incorrect_id() {
+async fn test_incorrect_receipt() {
     let mut app = setup_test_app().into_service();
 
-    let delete_request =
-        create_post_request("/delete", json!({"ids": ["invalid-id1", "invalid-id2"]}));
+    let delete_request = create_post_request(
+        "/delete",
+        json!({"receipts": ["invalid-id1", "invalid-id2"]}),
+    );
     let response = send_request(&mut app, delete_request).await;
     assert_eq!(response.status(), StatusCode::BAD_REQUEST);
 
@@ -36,6 +53,42 @@ async fn test_incorrect_id() {
     let body_text = String::from_utf8(body.to_vec()).unwrap();
     assert_eq!(
         body_text,
-        "Invalid message IDs: [\"invalid-id1\", \"invalid-id2\"]"
+        "Invalid receipts: [\"invalid-id1\", \"invalid-id2\"]"
     );
 }
+
+#[tokio::test]
+async fn test_delete_with_stale_receipt_keeps_redelivered_message() {
+    let mut app = setup_test_app().into_service();
+
+    let add_request = create_post_request("/add", json!({"body": "message 1"}));
+    send_request(&mut app, add_request).await;
+
+    let get_request = create_post_request("/get", json!({"count": 1}));
+    let response = send_request(&mut app, get_request).await;
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let first: Vec<Message> = serde_json::from_slice(&body).unwrap();
+    let stale = first[0].receipt.clone().unwrap();
+
+    // The message goes back to the queue and is delivered to another consumer
+    let retry_request = create_post_request("/retry", json!({"receipts": [stale]}));
+    send_request(&mut app, retry_request).await;
+    let get_request = create_post_request("/get", json!({"count": 1}));
+    let response = send_request(&mut app, get_request).await;
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let second: Vec<Message> = serde_json::from_slice(&body).unwrap();
+    assert_eq!(second[0].id, first[0].id);
+    assert_ne!(second[0].receipt, first[0].receipt);
+
+    let delete_request = create_post_request("/delete", json!({"receipts": [stale]}));
+    let response = send_request(&mut app, delete_request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let outcomes: Vec<ReceiptOutcome> = serde_json::from_slice(&body).unwrap();
+    assert_eq!(outcomes[0].status, ReceiptStatus::LockExpired);
+
+    let delete_request = create_post_request("/delete", json!({"receipts": [second[0].receipt]}));
+    let response = send_request(&mut app, delete_request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
@@ -0,0 +1,75 @@
+use crate::common::{create_post_request, send_request, setup_test_app};
+use http::StatusCode;
+use http_body_util::BodyExt;
+use serde_json::json;
+use tlq::types::Message;
+
+#[tokio::test]
+async fn test_extend_pushes_lock_forward() {
+    let mut app = setup_test_app().into_service();
+
+    let add_request = create_post_request("/add", json!({"body": "long-running job"}));
+    send_request(&mut app, add_request).await;
+
+    let get_request = create_post_request("/get", json!({"count": 1}));
+    let response = send_request(&mut app, get_request).await;
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let messages: Vec<Message> = serde_json::from_slice(&body).unwrap();
+    let lock_until = messages[0].lock_until.unwrap();
+
+    let extend_request = create_post_request(
+        "/extend",
+        json!({"receipts": [messages[0].receipt], "extend_secs": 120}),
+    );
+    let response = send_request(&mut app, extend_request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let extended: Vec<Message> = serde_json::from_slice(&body).unwrap();
+    assert_eq!(extended.len(), 1);
+    assert_eq!(extended[0].id, messages[0].id);
+    assert_eq!(extended[0].lock_until, Some(lock_until + 120_000));
+}
+
+#[tokio::test]
+async fn test_extend_released_message_returns_conflict() {
+    let mut app = setup_test_app().into_service();
+
+    let add_request = create_post_request("/add", json!({"body": "not taken"}));
+    send_request(&mut app, add_request).await;
+
+    let get_request = create_post_request("/get", json!({"count": 1}));
+    let response = send_request(&mut app, get_request).await;
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let messages: Vec<Message> = serde_json::from_slice(&body).unwrap();
+    let receipt = messages[0].receipt.clone().unwrap();
+
+    let retry_request = create_post_request("/retry", json!({"receipts": [receipt]}));
+    send_request(&mut app, retry_request).await;
+
+    let extend_request = create_post_request(
+        "/extend",
+        json!({"receipts": [receipt], "extend_secs": 120}),
+    );
+    let response = send_request(&mut app, extend_request).await;
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body_text = String::from_utf8(body.to_vec()).unwrap();
+    assert_eq!(
+        body_text,
+        format!("Receipts expired or no longer valid: [\"{receipt}\"]")
+    );
+}
+
+#[tokio::test]
+async fn test_extend_with_zero_seconds_returns_bad_request() {
+    let mut app = setup_test_app().into_service();
+
+    let extend_request = create_post_request(
+        "/extend",
+        json!({"receipts": ["0198a2f0-0000-7000-8000-000000000000.token"], "extend_secs": 0}),
+    );
+    let response = send_request(&mut app, extend_request).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
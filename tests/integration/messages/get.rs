@@ -2,7 +2,9 @@ use crate::common::{create_post_request, send_request, setup_test_app};
 use http::StatusCode;
 use http_body_util::BodyExt;
 use serde_json::json;
+use std::time::{SystemTime, UNIX_EPOCH};
 use tlq::types::Message;
+
 #[tokio::test]
 async fn test_get_messages() {
     let mut app = setup_test_app().into_service();
@@ -32,3 +34,108 @@ async fn test_get_messages() {
     let body_json = serde_json::from_slice::<Vec<Message>>(&body).unwrap();
     assert_eq!(body_json.len(), 2);
 }
+
+#[tokio::test]
+async fn test_get_messages_by_priority() {
+    let mut app = setup_test_app().into_service();
+
+    for (body, priority) in [("low", -5), ("normal 1", 0), ("high", 10), ("normal 2", 0)] {
+        let post_request = create_post_request("/add", json!({"body": body, "priority": priority}));
+        send_request(&mut app, post_request).await;
+    }
+
+    let get_request = create_post_request("/get", json!({"count": 4}));
+    let response = send_request(&mut app, get_request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body_json = serde_json::from_slice::<Vec<Message>>(&body).unwrap();
+    let bodies: Vec<&str> = body_json.iter().map(|m| m.body.as_str()).collect();
+    assert_eq!(bodies, vec!["high", "normal 1", "normal 2", "low"]);
+    assert_eq!(body_json[0].priority, 10);
+}
+
+#[tokio::test]
+async fn test_get_messages_one_per_group() {
+    let mut app = setup_test_app().into_service();
+
+    for (body, group_id) in [("a1", "a"), ("a2", "a"), ("b1", "b")] {
+        let post_request = create_post_request("/add", json!({"body": body, "group_id": group_id}));
+        send_request(&mut app, post_request).await;
+    }
+
+    let get_request = create_post_request("/get", json!({"count": 3}));
+    let response = send_request(&mut app, get_request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body_json = serde_json::from_slice::<Vec<Message>>(&body).unwrap();
+    let bodies: Vec<&str> = body_json.iter().map(|m| m.body.as_str()).collect();
+    assert_eq!(bodies, vec!["a1", "b1"]);
+    assert_eq!(body_json[0].group_id.as_deref(), Some("a"));
+
+    let get_request = create_post_request("/get", json!({"count": 3}));
+    let response = send_request(&mut app, get_request).await;
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body_json = serde_json::from_slice::<Vec<Message>>(&body).unwrap();
+    assert!(body_json.is_empty());
+}
+
+#[tokio::test]
+async fn test_get_messages_returns_attributes() {
+    let mut app = setup_test_app().into_service();
+
+    let attributes = json!({"content-type": "application/json", "tenant": "acme"});
+    let post_request = create_post_request("/add", json!({"body": "{}", "attributes": attributes}));
+    send_request(&mut app, post_request).await;
+
+    let get_request = create_post_request("/get", json!({"count": 1}));
+    let response = send_request(&mut app, get_request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body_json = serde_json::from_slice::<serde_json::Value>(&body).unwrap();
+    assert_eq!(body_json[0]["attributes"], attributes);
+}
+
+#[tokio::test]
+async fn test_get_messages_with_lock_secs() {
+    let mut app = setup_test_app().into_service();
+
+    let post_request = create_post_request("/add", json!({"body": "quick job"}));
+    send_request(&mut app, post_request).await;
+
+    let get_request = create_post_request("/get", json!({"count": 1, "lock_secs": 5}));
+    let response = send_request(&mut app, get_request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as i64;
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body_json = serde_json::from_slice::<Vec<Message>>(&body).unwrap();
+    assert!(body_json[0].lock_until.unwrap() <= now + 5_000);
+}
+
+#[tokio::test]
+async fn test_get_messages_with_lock_secs_above_maximum_returns_bad_request() {
+    let mut app = setup_test_app().into_service();
+
+    let get_request = create_post_request("/get", json!({"count": 1, "lock_secs": 43201}));
+    let response = send_request(&mut app, get_request).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_get_messages_with_wait_secs_above_maximum_returns_bad_request() {
+    let mut app = setup_test_app().into_service();
+
+    let get_request = create_post_request("/get", json!({"count": 1, "wait_secs": 21}));
+    let response = send_request(&mut app, get_request).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body_text = String::from_utf8(body.to_vec()).unwrap();
+    assert_eq!(body_text, "wait_secs must be at most 20");
+}
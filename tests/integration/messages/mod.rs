@@ -1,5 +1,7 @@
 pub mod add;
+mod browse;
 mod delete;
+mod extend;
 pub mod get;
 mod purge;
 mod retry;
@@ -2,7 +2,7 @@ use crate::common::{create_post_request, send_request, setup_test_app};
 use http::StatusCode;
 use http_body_util::BodyExt;
 use serde_json::json;
-use tlq::types::Message;
+use tlq::types::{Message, ReceiptOutcome, ReceiptStatus};
 
 #[tokio::test]
 async fn test_purge_messages() {
@@ -53,12 +53,16 @@ async fn test_purge_with_processing() {
     let response = send_request(&mut app, purge_request).await;
     assert_eq!(response.status(), StatusCode::OK);
 
-    // Retry 2 fetched messages
-    let message_ids: Vec<_> = messages.iter().map(|m| m.id).collect();
-    let retry_request = create_post_request("/retry", json!({"ids": message_ids}));
+    // Retry 2 fetched messages, which no longer exist
+    let receipts: Vec<_> = messages.iter().map(|m| m.receipt.clone()).collect();
+    let retry_request = create_post_request("/retry", json!({"receipts": receipts}));
     let response = send_request(&mut app, retry_request).await;
     assert_eq!(response.status(), StatusCode::OK);
 
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let outcomes = serde_json::from_slice::<Vec<ReceiptOutcome>>(&body).unwrap();
+    assert!(outcomes.iter().all(|o| o.status == ReceiptStatus::NotFound));
+
     // Verify that no messages are left
     let get_request = create_post_request("/get", json!({"count": 10}));
     let response = send_request(&mut app, get_request).await;
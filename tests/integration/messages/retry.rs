@@ -2,7 +2,7 @@ use crate::common::{create_post_request, send_request, setup_test_app};
 use http::StatusCode;
 use http_body_util::BodyExt;
 use serde_json::json;
-use tlq::types::Message;
+use tlq::types::{Message, ReceiptOutcome, ReceiptStatus};
 
 #[tokio::test]
 async fn test_retry_messages_and_get_same_message() {
@@ -22,13 +22,14 @@ async fn test_retry_messages_and_get_same_message() {
     assert!(!messages.is_empty());
 
     // Retry the message
-    let retry_request = create_post_request("/retry", json!({"ids": [messages[0].id]}));
+    let retry_request = create_post_request("/retry", json!({"receipts": [messages[0].receipt]}));
     let response = send_request(&mut app, retry_request).await;
     assert_eq!(response.status(), StatusCode::OK);
 
     let body = response.into_body().collect().await.unwrap().to_bytes();
-    let result: serde_json::Value = serde_json::from_slice(&body).unwrap();
-    assert_eq!(result, json!("Success"));
+    let outcomes: Vec<ReceiptOutcome> = serde_json::from_slice(&body).unwrap();
+    assert_eq!(outcomes.len(), 1);
+    assert_eq!(outcomes[0].status, ReceiptStatus::Retried);
 
     // Verify the same message can be retrieved after retry
     let get_request = create_post_request("/get", json!({"count": 1}));
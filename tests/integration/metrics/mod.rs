@@ -0,0 +1,33 @@
+use crate::common::{create_get_request, create_post_request, send_request, setup_test_app};
+use http::StatusCode;
+use http_body_util::BodyExt;
+use serde_json::json;
+
+#[tokio::test]
+async fn test_metrics_exposes_queue_gauges_and_counters() {
+    let mut app = setup_test_app().into_service();
+
+    for body in ["first", "second"] {
+        let request = create_post_request("/queues/scraped/add", json!({"body": body}));
+        send_request(&mut app, request).await;
+    }
+    let request = create_post_request("/queues/scraped/get", json!({"count": 1}));
+    send_request(&mut app, request).await;
+
+    let response = send_request(&mut app, create_get_request("/metrics")).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers()[http::header::CONTENT_TYPE],
+        "text/plain; version=0.0.4"
+    );
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let output = String::from_utf8(body.to_vec()).unwrap();
+    assert!(output.contains("tlq_ready_messages{queue=\"scraped\"} 1"));
+    assert!(output.contains("tlq_processing_messages{queue=\"scraped\"} 1"));
+    assert!(output.contains("tlq_dead_messages{queue=\"scraped\"} 0"));
+    assert!(output.contains("tlq_messages_added_total{queue=\"scraped\"} 2"));
+    assert!(output.contains("tlq_messages_delivered_total{queue=\"scraped\"} 1"));
+    assert!(output.contains("tlq_message_size_bytes_count{queue=\"scraped\"} 2"));
+    assert!(output.contains("route=\"/queues/{queue}/add\",status=\"200\""));
+}
@@ -1,3 +1,7 @@
+pub mod dead;
 pub mod healthcheck;
 pub mod messages;
+pub mod metrics;
+pub mod queues;
+pub mod snapshot;
 pub mod stats;
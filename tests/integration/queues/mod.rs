@@ -0,0 +1,191 @@
+use crate::common::{
+    create_delete_request, create_get_request, create_post_request, send_request, setup_test_app,
+};
+use http::StatusCode;
+use http_body_util::BodyExt;
+use serde_json::json;
+use tlq::types::Message;
+
+#[tokio::test]
+async fn test_create_and_list_queues() {
+    let mut app = setup_test_app().into_service();
+
+    let request = create_post_request("/queues", json!({"name": "orders"}));
+    let response = send_request(&mut app, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = send_request(&mut app, create_get_request("/queues")).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body_json = serde_json::from_slice::<serde_json::Value>(&body).unwrap();
+    let queues = body_json.as_array().unwrap();
+    assert_eq!(queues.len(), 2);
+    assert_eq!(queues[0]["name"], json!("default"));
+    assert_eq!(queues[1]["name"], json!("orders"));
+    assert_eq!(queues[1]["ready"], json!(0));
+    assert_eq!(queues[1]["processing"], json!(0));
+    assert_eq!(queues[1]["dead"], json!(0));
+    assert_eq!(queues[1]["config"], queues[0]["config"]);
+}
+
+#[tokio::test]
+async fn test_create_queue_with_config() {
+    let mut app = setup_test_app().into_service();
+
+    let request = create_post_request(
+        "/queues",
+        json!({"name": "slow", "lock_duration_secs": 600, "max_retries": 10}),
+    );
+    let response = send_request(&mut app, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body_json = serde_json::from_slice::<serde_json::Value>(&body).unwrap();
+    assert_eq!(body_json["lock_duration_secs"], json!(600));
+    assert_eq!(body_json["max_retries"], json!(10));
+
+    let response = send_request(&mut app, create_get_request("/queues/slow/config")).await;
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let config = serde_json::from_slice::<serde_json::Value>(&body).unwrap();
+    assert_eq!(config, body_json);
+}
+
+#[tokio::test]
+async fn test_create_existing_queue_returns_conflict() {
+    let mut app = setup_test_app().into_service();
+
+    let request = create_post_request("/queues", json!({"name": "orders"}));
+    send_request(&mut app, request).await;
+
+    let request = create_post_request("/queues", json!({"name": "orders"}));
+    let response = send_request(&mut app, request).await;
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    assert_eq!(body, "Queue 'orders' already exists");
+}
+
+#[tokio::test]
+async fn test_update_queue_config() {
+    let mut app = setup_test_app().into_service();
+
+    let request = create_post_request("/queues/small/add", json!({"body": "12345"}));
+    send_request(&mut app, request).await;
+
+    let request = create_post_request("/queues/small/config", json!({"max_message_size": 4}));
+    let response = send_request(&mut app, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body_json = serde_json::from_slice::<serde_json::Value>(&body).unwrap();
+    assert_eq!(body_json["max_message_size"], json!(4));
+
+    let request = create_post_request("/queues/small/add", json!({"body": "12345"}));
+    let response = send_request(&mut app, request).await;
+    assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+}
+
+#[tokio::test]
+async fn test_update_queue_config_rejects_zero_lock_duration() {
+    let mut app = setup_test_app().into_service();
+
+    let request = create_post_request("/config", json!({"lock_duration_secs": 0}));
+    let response = send_request(&mut app, request).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_get_config_of_unknown_queue() {
+    let mut app = setup_test_app().into_service();
+
+    let response = send_request(&mut app, create_get_request("/queues/missing/config")).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_create_queue_with_invalid_name() {
+    let mut app = setup_test_app().into_service();
+
+    let request = create_post_request("/queues", json!({"name": "orders/eu"}));
+    let response = send_request(&mut app, request).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_add_creates_queue_implicitly() {
+    let mut app = setup_test_app().into_service();
+
+    let request = create_post_request("/queues/emails/add", json!({"body": "welcome"}));
+    let response = send_request(&mut app, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = send_request(&mut app, create_get_request("/queues/emails/stats")).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body_json = serde_json::from_slice::<serde_json::Value>(&body).unwrap();
+    assert_eq!(body_json["ready"], json!(1));
+}
+
+#[tokio::test]
+async fn test_queues_are_isolated() {
+    let mut app = setup_test_app().into_service();
+
+    let request = create_post_request("/queues/emails/add", json!({"body": "welcome"}));
+    send_request(&mut app, request).await;
+    let request = create_post_request("/add", json!({"body": "default message"}));
+    send_request(&mut app, request).await;
+
+    let request = create_post_request("/queues/emails/get", json!({"count": 10}));
+    let response = send_request(&mut app, request).await;
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let messages = serde_json::from_slice::<Vec<Message>>(&body).unwrap();
+    assert_eq!(messages.len(), 1);
+    assert_eq!(messages[0].body, "welcome");
+
+    let response = send_request(&mut app, create_get_request("/stats")).await;
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body_json = serde_json::from_slice::<serde_json::Value>(&body).unwrap();
+    assert_eq!(body_json["ready"], json!(1));
+    assert_eq!(body_json["processing"], json!(0));
+}
+
+#[tokio::test]
+async fn test_get_from_unknown_queue() {
+    let mut app = setup_test_app().into_service();
+
+    let request = create_post_request("/queues/missing/get", json!({"count": 1}));
+    let response = send_request(&mut app, request).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body_text = String::from_utf8(body.to_vec()).unwrap();
+    assert_eq!(body_text, "Queue 'missing' not found");
+}
+
+#[tokio::test]
+async fn test_delete_queue() {
+    let mut app = setup_test_app().into_service();
+
+    let request = create_post_request("/queues/temp/add", json!({"body": "temporary"}));
+    send_request(&mut app, request).await;
+
+    let response = send_request(&mut app, create_delete_request("/queues/temp")).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = send_request(&mut app, create_get_request("/queues/temp/stats")).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_delete_default_queue_is_rejected() {
+    let mut app = setup_test_app().into_service();
+
+    let response = send_request(&mut app, create_delete_request("/queues/default")).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body_text = String::from_utf8(body.to_vec()).unwrap();
+    assert_eq!(body_text, "The default queue cannot be deleted");
+}
@@ -0,0 +1,117 @@
+use crate::common::{
+    create_binary_post_request, create_get_request, create_post_request, send_request,
+    setup_test_app,
+};
+use http::StatusCode;
+use http_body_util::BodyExt;
+use serde_json::json;
+
+#[tokio::test]
+async fn test_export_and_import_round_trip() {
+    let mut app = setup_test_app().into_service();
+
+    for body in ["first", "second"] {
+        let request = create_post_request("/queues/source/add", json!({"body": body}));
+        send_request(&mut app, request).await;
+    }
+    let request = create_post_request("/queues/source/get", json!({"count": 1}));
+    send_request(&mut app, request).await;
+
+    let response = send_request(&mut app, create_get_request("/queues/source/snapshot")).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers()[http::header::CONTENT_TYPE],
+        "application/x-ndjson"
+    );
+    let snapshot = response.into_body().collect().await.unwrap().to_bytes();
+    let header: serde_json::Value =
+        serde_json::from_str(String::from_utf8_lossy(&snapshot).lines().next().unwrap()).unwrap();
+    assert_eq!(header["version"], 1);
+    assert_eq!(header["queue"], "source");
+    assert_eq!(header["messages"], 2);
+
+    let request = create_binary_post_request("/queues/copy/snapshot", snapshot.to_vec());
+    let response = send_request(&mut app, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let summary: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(
+        summary,
+        json!({"imported": 2, "dead_letters": 0, "skipped": 0})
+    );
+
+    // The processing message was requeued, so both are ready in the copy
+    let response = send_request(&mut app, create_get_request("/queues/copy/stats")).await;
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let stats: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(stats["ready"], 2);
+    assert_eq!(stats["processing"], 0);
+}
+
+#[tokio::test]
+async fn test_import_keeps_processing_and_skips_duplicates() {
+    let mut app = setup_test_app().into_service();
+
+    let request = create_post_request("/queues/locked/add", json!({"body": "held"}));
+    send_request(&mut app, request).await;
+    let request = create_post_request("/queues/locked/get", json!({"count": 1}));
+    send_request(&mut app, request).await;
+
+    let response = send_request(&mut app, create_get_request("/queues/locked/snapshot")).await;
+    let snapshot = response.into_body().collect().await.unwrap().to_bytes();
+
+    let request = create_binary_post_request(
+        "/queues/restored/snapshot?processing=keep",
+        snapshot.to_vec(),
+    );
+    send_request(&mut app, request).await;
+    let response = send_request(&mut app, create_get_request("/queues/restored/stats")).await;
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let stats: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(stats["processing"], 1);
+
+    let request = create_binary_post_request("/queues/restored/snapshot", snapshot.to_vec());
+    let response = send_request(&mut app, request).await;
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let summary: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(
+        summary,
+        json!({"imported": 0, "dead_letters": 0, "skipped": 1})
+    );
+}
+
+#[tokio::test]
+async fn test_import_rejects_invalid_snapshot() {
+    let mut app = setup_test_app().into_service();
+
+    let request = create_binary_post_request("/snapshot", b"not json\n".to_vec());
+    let response = send_request(&mut app, request).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body_text = String::from_utf8(body.to_vec()).unwrap();
+    assert!(body_text.starts_with("Invalid snapshot header"));
+}
+
+#[tokio::test]
+async fn test_export_unknown_queue() {
+    let mut app = setup_test_app().into_service();
+
+    let response = send_request(&mut app, create_get_request("/queues/missing/snapshot")).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_import_body_limit() {
+    let mut app = setup_test_app().into_service();
+
+    // Larger than axum's default body limit, so it reaches snapshot parsing
+    let request = create_binary_post_request("/snapshot", vec![b'x'; 4 * 1024 * 1024]);
+    let response = send_request(&mut app, request).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    // Larger than the default TLQ_MAX_SNAPSHOT_SIZE
+    let request = create_binary_post_request("/snapshot", vec![b'x'; 64 * 1024 * 1024 + 1]);
+    let response = send_request(&mut app, request).await;
+    assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+}
@@ -40,4 +40,10 @@ async fn test_stats_with_ready_and_processing_messages() {
 
     assert_eq!(body_json["ready"], json!(3));
     assert_eq!(body_json["processing"], json!(2));
+    assert_eq!(body_json["enqueued"], json!(5));
+    assert_eq!(body_json["acked"], json!(0));
+    assert_eq!(body_json["bytes"], json!(5 * "message 1".len()));
+    assert!(body_json["oldest_ready_age_ms"].is_i64());
+    assert!(body_json["oldest_lock_until"].is_i64());
+    assert!(body_json["enqueue_rate"].as_f64().unwrap() > 0.0);
 }